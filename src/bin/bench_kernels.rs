@@ -0,0 +1,132 @@
+//! Compares the evaluation kernels on nested `Vec<Vec<usize>>` matrices, as they were before
+//! the flat `Matrix` type, with the current ones, on a random asymmetric instance and on a
+//! random symmetric one.
+//!
+//! Usage: `cargo run --release --bin bench_kernels [size] [repetitions]`
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use quadratic_assignment_problem::{
+    get_random_pair, get_random_permutation,
+    instance::Instance,
+    solver::{delta_matrix::swap_delta, dot_product_permuted_with_swap},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+type NestedMatrix = Vec<Vec<usize>>;
+
+const ROUNDS: usize = 7;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let size: usize = args.next().map_or(100, |arg| arg.parse().expect("Size should be a number"));
+    let repetitions: usize = args.next().map_or(2000, |arg| arg.parse().expect("Repetitions should be a number"));
+    let mut rng = StdRng::seed_from_u64(42);
+
+    for symmetric in [false, true] {
+        let a = random_matrix(size, symmetric, &mut rng);
+        let b = random_matrix(size, symmetric, &mut rng);
+        let instance = Instance::new(a.clone(), b.clone(), 0, vec![]);
+        let perms: Vec<Vec<usize>> = (0..repetitions).map(|_| get_random_permutation(size, &mut rng)).collect();
+        let pairs: Vec<(usize, usize)> = (0..repetitions).map(|_| get_random_pair(size, &mut rng)).collect();
+
+        println!("n = {size}, symmetric = {symmetric}");
+        compare(
+            "evaluate",
+            repetitions,
+            |r| nested_evaluate(&a, &b, &perms[r]) as i64,
+            |r| instance.evaluate_wide(&perms[r]),
+        );
+        compare(
+            "swap delta",
+            repetitions,
+            |r| nested_swap_delta(&a, &b, &perms[r], pairs[r].0, pairs[r].1) as i64,
+            |r| swap_delta(&instance, &perms[r], pairs[r].0, pairs[r].1),
+        );
+        compare(
+            "dot product with swap",
+            repetitions,
+            |r| {
+                let (i, j) = pairs[r];
+                nested_dot_product_with_swap(&a[i], &b[perms[r][j]], &perms[r], i, j) as i64
+            },
+            |r| {
+                let (i, j) = pairs[r];
+                dot_product_permuted_with_swap(instance.matrix_a.row(i), instance.matrix_b.row(perms[r][j]), &perms[r], i, j)
+            },
+        );
+    }
+}
+
+/// Times both kernels over the same inputs, checking that they agree. The kernels take turns
+/// for a few rounds and the fastest round of each counts, which keeps other load on the
+/// machine from skewing the comparison.
+fn compare(name: &str, repetitions: usize, old: impl Fn(usize) -> i64, new: impl Fn(usize) -> i64) {
+    let time = |kernel: &dyn Fn(usize) -> i64| {
+        let start = Instant::now();
+        let results: Vec<i64> = (0..repetitions).map(|r| black_box(kernel(black_box(r)))).collect();
+        (start.elapsed().as_nanos() / repetitions.max(1) as u128, results)
+    };
+    let (mut old_time, mut new_time) = (u128::MAX, u128::MAX);
+    for _ in 0..ROUNDS {
+        let (old_round, old_results) = time(&old);
+        let (new_round, new_results) = time(&new);
+        assert_eq!(old_results, new_results, "{name} kernels disagree");
+        old_time = old_time.min(old_round);
+        new_time = new_time.min(new_round);
+    }
+    println!(
+        "  {name:<24} old {old_time:>8} ns  new {new_time:>8} ns  speedup {:.2}",
+        old_time as f64 / new_time.max(1) as f64
+    );
+}
+
+fn random_matrix(size: usize, symmetric: bool, rng: &mut StdRng) -> NestedMatrix {
+    let random: NestedMatrix = (0..size).map(|_| (0..size).map(|_| rng.gen_range(0..100)).collect()).collect();
+    if !symmetric {
+        return random;
+    }
+    (0..size)
+        .map(|i| (0..size).map(|j| random[i.min(j)][i.max(j)]).collect())
+        .collect()
+}
+
+fn nested_dot_product(row_a: &[usize], row_b: &[usize], perm: &[usize]) -> usize {
+    row_a.iter().zip(perm.iter().map(|&index| row_b[index])).map(|(&a, b)| a * b).sum()
+}
+
+fn nested_evaluate(a: &NestedMatrix, b: &NestedMatrix, perm: &[usize]) -> usize {
+    a.iter()
+        .zip(perm.iter().map(|&index| &b[index]))
+        .map(|(row_a, row_b)| nested_dot_product(row_a, row_b, perm))
+        .sum()
+}
+
+fn nested_dot_product_with_swap(row_a: &[usize], row_b: &[usize], perm: &[usize], i: usize, j: usize) -> usize {
+    let mut sum = 0;
+    for (k, &a) in row_a.iter().enumerate() {
+        if k == i {
+            sum += a * row_b[perm[j]];
+        } else if k == j {
+            sum += a * row_b[perm[i]];
+        } else {
+            sum += a * row_b[perm[k]];
+        }
+    }
+    sum
+}
+
+fn nested_swap_delta(a: &NestedMatrix, b: &NestedMatrix, perm: &[usize], i: usize, j: usize) -> i64 {
+    let v = |value: usize| value as i64;
+    let (pi, pj) = (perm[i], perm[j]);
+    let mut delta = (v(a[i][i]) - v(a[j][j])) * (v(b[pj][pj]) - v(b[pi][pi]))
+        + (v(a[i][j]) - v(a[j][i])) * (v(b[pj][pi]) - v(b[pi][pj]));
+    for (k, &pk) in perm.iter().enumerate() {
+        if k != i && k != j {
+            delta += (v(a[k][i]) - v(a[k][j])) * (v(b[pk][pj]) - v(b[pk][pi]))
+                + (v(a[i][k]) - v(a[j][k])) * (v(b[pj][pk]) - v(b[pi][pk]));
+        }
+    }
+    -delta
+}
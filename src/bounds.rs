@@ -0,0 +1,416 @@
+//! Lower bounds on the optimal cost of an instance, used to judge solution quality when no
+//! optimal solution is known.
+
+use crate::{
+    cost::{cmp_costs, Cost},
+    instance::{Formulation, Instance},
+    lap,
+    matrix::Matrix,
+};
+
+/// Relative tolerance applied before rounding the floating point bounds up to integers.
+const ROUNDING_TOLERANCE: f64 = 1e-9;
+const JACOBI_MAX_SWEEPS: usize = 100;
+
+#[derive(Debug)]
+pub struct Bounds<C: Cost = usize> {
+    pub gilmore_lawler: C::Wide,
+    /// Finke–Burkard–Rendl eigenvalue bound; `None` when neither matrix is symmetric or the
+    /// instance has the Lawler form.
+    pub eigenvalue: Option<f64>,
+    /// Hadley–Rendl–Wolkowicz projection bound; `None` when neither matrix is symmetric or
+    /// the instance has the Lawler form.
+    pub projection: Option<f64>,
+}
+
+impl<C: Cost> Bounds<C> {
+    pub fn compute(instance: &Instance<C>) -> Bounds<C> {
+        let symmetric = symmetrized(instance);
+        let linear = |i: usize, k: usize| instance.get_linear_costs().map_or(0.0, |costs| costs[i][k].to_f64());
+        Bounds {
+            gilmore_lawler: gilmore_lawler(instance),
+            eigenvalue: symmetric.as_ref().map(|(a, b)| eigenvalue(a, b, linear)),
+            projection: symmetric.as_ref().map(|(a, b)| projection(a, b, linear)),
+        }
+    }
+
+    /// The tightest of the bounds, rounded up to the next attainable cost.
+    pub fn best(&self) -> C {
+        let real_bounds = [self.eigenvalue, self.projection];
+        real_bounds
+            .iter()
+            .flatten()
+            .map(|&bound| C::ceil_from_f64(bound - ROUNDING_TOLERANCE * bound.abs().max(1.0)))
+            .chain(C::narrow(self.gilmore_lawler).ok())
+            .max_by(cmp_costs)
+            .unwrap_or_default()
+    }
+}
+
+/// Gilmore–Lawler bound of the whole instance.
+pub fn gilmore_lawler<C: Cost>(instance: &Instance<C>) -> C::Wide {
+    let facilities: Vec<usize> = (0..instance.size).collect();
+    gilmore_lawler_partial(instance, &facilities, &[])
+}
+
+/// Gilmore–Lawler bound of every completion of a partial assignment where facility
+/// `facilities[t]` sits at `locations[t]`. Only the first `locations.len()` entries of
+/// `facilities` are assigned; it must list every facility.
+pub fn gilmore_lawler_partial<C: Cost>(instance: &Instance<C>, facilities: &[usize], locations: &[usize]) -> C::Wide {
+    let a = &instance.matrix_a;
+    let b = &instance.matrix_b;
+    let n = instance.size;
+    let assigned: Vec<(usize, usize)> = facilities.iter().copied().zip(locations.iter().copied()).collect();
+    // Cost of facility `i` at `k` together with facility `j` at `l`, or of `i` alone at `k`
+    // when `i == j` and `k == l`.
+    let pair_cost = |i: usize, j: usize, k: usize, l: usize| -> C::Wide {
+        let quadratic = a[i][j].widen() * b[k][l].widen();
+        match instance.get_formulation() {
+            Formulation::KoopmansBeckmann => quadratic,
+            Formulation::Linear(costs) if i == j && k == l => quadratic + costs[i][k].widen(),
+            Formulation::Linear(_) => quadratic,
+            Formulation::Lawler(costs) => costs.get(i, j, k, l).widen(),
+        }
+    };
+
+    let fixed: C::Wide = assigned
+        .iter()
+        .flat_map(|&(i, k)| assigned.iter().map(move |&(j, l)| pair_cost(i, j, k, l)))
+        .sum();
+    if assigned.len() == n {
+        return fixed;
+    }
+
+    let mut is_free = vec![true; n];
+    locations.iter().for_each(|&k| is_free[k] = false);
+    let free_facilities = &facilities[locations.len()..];
+    let free_locations: Vec<usize> = (0..n).filter(|&k| is_free[k]).collect();
+
+    // Flows and distances towards the other unassigned items, sorted so that the minimal
+    // scalar product pairs the largest flows with the smallest distances.
+    let flows: Vec<Vec<C::Wide>> = free_facilities
+        .iter()
+        .map(|&i| {
+            let mut row: Vec<C::Wide> =
+                free_facilities.iter().filter(|&&j| j != i).map(|&j| a[i][j].widen()).collect();
+            row.sort_unstable_by(cmp_costs);
+            row
+        })
+        .collect();
+    let distances: Vec<Vec<C::Wide>> = free_locations
+        .iter()
+        .map(|&k| {
+            let mut row: Vec<C::Wide> =
+                free_locations.iter().filter(|&&l| l != k).map(|&l| b[k][l].widen()).collect();
+            row.sort_unstable_by(|x, y| cmp_costs(y, x));
+            row
+        })
+        .collect();
+    // Without the product structure every other unassigned facility is bounded by its
+    // cheapest free location on its own.
+    let quadratic = |i: usize, k: usize, flow_row: &[C::Wide], distance_row: &[C::Wide]| -> C::Wide {
+        match instance.get_formulation() {
+            Formulation::Lawler(costs) => free_facilities
+                .iter()
+                .filter(|&&j| j != i)
+                .map(|&j| {
+                    free_locations
+                        .iter()
+                        .filter(|&&l| l != k)
+                        .map(|&l| costs.get(i, j, k, l).widen())
+                        .min_by(cmp_costs)
+                        .unwrap_or_default()
+                })
+                .sum(),
+            _ => flow_row.iter().zip(distance_row.iter()).map(|(&f, &d)| f * d).sum(),
+        }
+    };
+
+    let costs: Vec<Vec<C::Wide>> = free_facilities
+        .iter()
+        .zip(flows.iter())
+        .map(|(&i, flow_row)| {
+            free_locations
+                .iter()
+                .zip(distances.iter())
+                .map(|(&k, distance_row)| {
+                    let linear: C::Wide = assigned
+                        .iter()
+                        .map(|&(j, l)| pair_cost(i, j, k, l) + pair_cost(j, i, l, k))
+                        .sum();
+                    pair_cost(i, i, k, k) + linear + quadratic(i, k, flow_row, distance_row)
+                })
+                .collect()
+        })
+        .collect();
+
+    fixed + lap::solve(&costs).cost
+}
+
+type RealMatrix = Vec<Vec<f64>>;
+
+/// Rewrites the instance with two symmetric matrices of the same cost. This works when at
+/// least one of the matrices is symmetric: the other one can then be replaced by the mean
+/// of itself and its transpose.
+fn symmetrized<C: Cost>(instance: &Instance<C>) -> Option<(RealMatrix, RealMatrix)> {
+    if let Formulation::Lawler(_) = instance.get_formulation() {
+        return None;
+    }
+    let to_real = |matrix: &Matrix<C>| -> RealMatrix {
+        matrix.rows().map(|row| row.iter().map(|x| x.to_f64()).collect()).collect()
+    };
+    let (a, b) = (to_real(&instance.matrix_a), to_real(&instance.matrix_b));
+    match (is_symmetric(&a), is_symmetric(&b)) {
+        (true, true) => Some((a, b)),
+        (false, true) => Some((symmetric_part(&a), b)),
+        (true, false) => Some((a, symmetric_part(&b))),
+        (false, false) => None,
+    }
+}
+
+/// Finke–Burkard–Rendl bound: the off-diagonal part is bounded by the minimal scalar
+/// product of the eigenvalues, the diagonal part and the `linear` term by a linear
+/// assignment problem.
+fn eigenvalue(a: &RealMatrix, b: &RealMatrix, linear: impl Fn(usize, usize) -> f64) -> f64 {
+    let (a_off, a_diag) = split_diagonal(a);
+    let (b_off, b_diag) = split_diagonal(b);
+    minimal_scalar_product(symmetric_eigenvalues(a_off), symmetric_eigenvalues(b_off))
+        + linear_bound(a.len(), |i, k| a_diag[i] * b_diag[k] + linear(i, k))
+}
+
+/// Hadley–Rendl–Wolkowicz projection bound. Projecting both off-diagonal parts onto the
+/// complement of the all-ones vector splits the cost into a quadratic term bounded by
+/// eigenvalues and a linear term in the row sums, solved exactly as a LAP together with the
+/// `linear` term of the instance.
+fn projection(a: &RealMatrix, b: &RealMatrix, linear: impl Fn(usize, usize) -> f64) -> f64 {
+    let n = a.len();
+    if n < 2 {
+        return eigenvalue(a, b, linear);
+    }
+    let (a_off, a_diag) = split_diagonal(a);
+    let (b_off, b_diag) = split_diagonal(b);
+    let a_row_sums: Vec<f64> = a_off.iter().map(|row| row.iter().sum()).collect();
+    let b_row_sums: Vec<f64> = b_off.iter().map(|row| row.iter().sum()).collect();
+    let a_total: f64 = a_row_sums.iter().sum();
+    let b_total: f64 = b_row_sums.iter().sum();
+
+    let quadratic = minimal_scalar_product(
+        symmetric_eigenvalues(project(&a_off)),
+        symmetric_eigenvalues(project(&b_off)),
+    );
+    let linear = linear_bound(n, |i, k| {
+        2.0 / n as f64 * a_row_sums[i] * b_row_sums[k] + a_diag[i] * b_diag[k] + linear(i, k)
+    });
+    quadratic + linear - a_total * b_total / (n * n) as f64
+}
+
+/// Lower bound of `min_p sum_i cost(i, p(i))` through the integer LAP solver. Costs are
+/// scaled before rounding down, so the result never exceeds the real minimum.
+fn linear_bound(n: usize, cost: impl Fn(usize, usize) -> f64) -> f64 {
+    const SCALE: f64 = 1024.0;
+    let costs: Vec<Vec<i64>> = (0..n)
+        .map(|i| (0..n).map(|k| (cost(i, k) * SCALE).floor() as i64).collect())
+        .collect();
+    lap::solve(&costs).cost as f64 / SCALE
+}
+
+/// `V^T M V` for an orthonormal basis `V` of the vectors orthogonal to the all-ones vector.
+/// `V` is taken from the Householder reflection mapping `e / sqrt(n)` onto the last unit
+/// vector, whose first `n - 1` columns span that complement.
+fn project(matrix: &RealMatrix) -> RealMatrix {
+    let n = matrix.len();
+    let mut w = vec![1.0 / (n as f64).sqrt(); n];
+    w[n - 1] -= 1.0;
+    let w_norm: f64 = w.iter().map(|x| x * x).sum();
+    let householder: RealMatrix = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| f64::from(u8::from(i == j)) - 2.0 * w[i] * w[j] / w_norm)
+                .collect()
+        })
+        .collect();
+    let reflected = multiply(&multiply(&householder, matrix), &householder);
+    reflected[..n - 1].iter().map(|row| row[..n - 1].to_vec()).collect()
+}
+
+fn multiply(left: &RealMatrix, right: &RealMatrix) -> RealMatrix {
+    let n = left.len();
+    let m = right.first().map_or(0, |row| row.len());
+    left.iter()
+        .map(|row| (0..m).map(|j| (0..n).map(|k| row[k] * right[k][j]).sum()).collect())
+        .collect()
+}
+
+fn split_diagonal(matrix: &RealMatrix) -> (RealMatrix, Vec<f64>) {
+    let diagonal = (0..matrix.len()).map(|i| matrix[i][i]).collect();
+    let mut off_diagonal = matrix.clone();
+    off_diagonal.iter_mut().enumerate().for_each(|(i, row)| row[i] = 0.0);
+    (off_diagonal, diagonal)
+}
+
+fn is_symmetric(matrix: &RealMatrix) -> bool {
+    (0..matrix.len()).all(|i| (0..i).all(|j| matrix[i][j] == matrix[j][i]))
+}
+
+fn symmetric_part(matrix: &RealMatrix) -> RealMatrix {
+    let n = matrix.len();
+    (0..n)
+        .map(|i| (0..n).map(|j| (matrix[i][j] + matrix[j][i]) / 2.0).collect())
+        .collect()
+}
+
+fn minimal_scalar_product(mut x: Vec<f64>, mut y: Vec<f64>) -> f64 {
+    x.sort_by(|p, q| p.total_cmp(q));
+    y.sort_by(|p, q| q.total_cmp(p));
+    x.iter().zip(y.iter()).map(|(p, q)| p * q).sum()
+}
+
+/// Eigenvalues of a symmetric matrix by the cyclic Jacobi method.
+fn symmetric_eigenvalues(mut matrix: RealMatrix) -> Vec<f64> {
+    let n = matrix.len();
+    let scale: f64 = matrix.iter().flatten().map(|x| x * x).sum::<f64>().max(f64::MIN_POSITIVE);
+
+    for _ in 0..JACOBI_MAX_SWEEPS {
+        let off_diagonal: f64 = (0..n)
+            .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| matrix[i][j] * matrix[i][j])
+            .sum();
+        if off_diagonal <= 1e-22 * scale {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if matrix[p][q] == 0.0 {
+                    continue;
+                }
+                let theta = (matrix[q][q] - matrix[p][p]) / (2.0 * matrix[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in matrix.iter_mut() {
+                    let (mkp, mkq) = (row[p], row[q]);
+                    row[p] = c * mkp - s * mkq;
+                    row[q] = s * mkp + c * mkq;
+                }
+                let (upper, lower) = matrix.split_at_mut(q);
+                for (mpk, mqk) in upper[p].iter_mut().zip(lower[0].iter_mut()) {
+                    let (x, y) = (*mpk, *mqk);
+                    *mpk = c * x - s * y;
+                    *mqk = s * x + c * y;
+                }
+            }
+        }
+    }
+    (0..n).map(|i| matrix[i][i]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::matrix::LawlerCosts;
+
+    fn all_costs<C: Cost>(instance: &Instance<C>, perm: &mut Vec<usize>, depth: usize, costs: &mut Vec<C>) {
+        if depth == perm.len() {
+            costs.push(instance.evaluate(perm).unwrap());
+            return;
+        }
+        for i in depth..perm.len() {
+            perm.swap(depth, i);
+            all_costs(instance, perm, depth + 1, costs);
+            perm.swap(depth, i);
+        }
+    }
+
+    #[test]
+    fn bounds_never_exceed_optimum() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for n in 2..=7 {
+            let mut symmetric_matrix = || -> Vec<Vec<usize>> {
+                let upper: Vec<Vec<usize>> =
+                    (0..n).map(|_| (0..n).map(|_| rng.gen_range(0..30)).collect()).collect();
+                (0..n)
+                    .map(|i| (0..n).map(|j| upper[i.min(j)][i.max(j)]).collect())
+                    .collect()
+            };
+            let instance = Instance::new(symmetric_matrix(), symmetric_matrix(), 0, vec![]);
+            let mut costs = Vec::new();
+            all_costs(&instance, &mut (0..n).collect(), 0, &mut costs);
+            let optimum = *costs.iter().min().unwrap();
+
+            let bounds = Bounds::compute(&instance);
+            assert!(bounds.gilmore_lawler <= optimum as i64);
+            assert!(bounds.eigenvalue.unwrap() <= optimum as f64 + 1e-6);
+            assert!(bounds.projection.unwrap() <= optimum as f64 + 1e-6);
+            assert!(bounds.best() <= optimum);
+        }
+    }
+
+    #[test]
+    fn bounds_hold_with_linear_and_lawler_costs() {
+        let mut rng = StdRng::seed_from_u64(6);
+        for n in 2..=6 {
+            let mut symmetric_matrix = || -> Vec<Vec<usize>> {
+                let upper: Vec<Vec<usize>> =
+                    (0..n).map(|_| (0..n).map(|_| rng.gen_range(0..30)).collect()).collect();
+                (0..n)
+                    .map(|i| (0..n).map(|j| upper[i.min(j)][i.max(j)]).collect())
+                    .collect()
+            };
+            let linear_costs = symmetric_matrix().into_iter().rev().collect();
+            let linear = Instance::with_linear_costs(symmetric_matrix(), symmetric_matrix(), linear_costs, 0, vec![]);
+            let lawler_costs = LawlerCosts::from_flat(n, (0..n.pow(4)).map(|_| rng.gen_range(0..30)).collect());
+            let lawler = Instance::from_lawler_costs(lawler_costs, 0, vec![]);
+
+            for instance in [&linear, &lawler] {
+                let mut costs = Vec::new();
+                all_costs(instance, &mut (0..n).collect(), 0, &mut costs);
+                let optimum = *costs.iter().min().unwrap();
+
+                let bounds = Bounds::compute(instance);
+                assert!(bounds.gilmore_lawler <= optimum as i64);
+                assert!(bounds.best() <= optimum);
+            }
+            assert!(Bounds::compute(&lawler).eigenvalue.is_none());
+        }
+    }
+
+    #[test]
+    fn bounds_hold_with_signed_and_real_costs() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for n in 2..=6 {
+            let mut signed_matrix =
+                || -> Vec<Vec<i64>> { (0..n).map(|_| (0..n).map(|_| rng.gen_range(-20..20)).collect()).collect() };
+            let signed = Instance::new(signed_matrix(), signed_matrix(), 0, vec![]);
+            let real = Instance::new(
+                signed.matrix_a.rows().map(|row| row.iter().map(|&x| x as f64 / 4.0).collect()).collect(),
+                signed.matrix_b.rows().map(|row| row.iter().map(|&x| x as f64 / 4.0).collect()).collect(),
+                0.0,
+                vec![],
+            );
+
+            let mut costs = Vec::new();
+            all_costs(&signed, &mut (0..n).collect(), 0, &mut costs);
+            let optimum = *costs.iter().min().unwrap();
+            let bounds = Bounds::compute(&signed);
+            assert!(bounds.gilmore_lawler <= optimum as i128);
+            assert!(bounds.best() <= optimum);
+
+            let bounds = Bounds::compute(&real);
+            assert!(bounds.gilmore_lawler <= optimum as f64 / 16.0 + 1e-9);
+            assert!(bounds.best() <= optimum as f64 / 16.0 + 1e-9);
+        }
+    }
+
+    #[test]
+    fn jacobi_finds_eigenvalues() {
+        let matrix = vec![vec![2.0, 1.0, 0.0], vec![1.0, 2.0, 0.0], vec![0.0, 0.0, 5.0]];
+        let mut eigenvalues = symmetric_eigenvalues(matrix);
+        eigenvalues.sort_by(|p, q| p.total_cmp(q));
+
+        for (expected, actual) in [1.0, 3.0, 5.0].iter().zip(eigenvalues.iter()) {
+            assert!((expected - actual).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Fixed and forbidden facility/location pairs an instance's solutions must respect.
+
+use std::fmt;
+
+use rand::Rng;
+
+use crate::get_random_permutation;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConstraintError {
+    /// `facility` is placed at a `location` it is forbidden from or not fixed to.
+    Violated { facility: usize, location: usize },
+    /// No permutation satisfies all the constraints.
+    Unsatisfiable,
+}
+
+impl fmt::Display for ConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstraintError::Violated { facility, location } => {
+                write!(f, "Facility {} may not be placed at location {}", facility, location)
+            }
+            ConstraintError::Unsatisfiable => write!(f, "No permutation satisfies the constraints"),
+        }
+    }
+}
+
+impl std::error::Error for ConstraintError {}
+
+/// Facilities pinned to a location and facility/location pairs that may not be used, for
+/// permutations of `size` locations. Fixing facility `i` to location `k` forbids every other
+/// location for `i` and every other facility at `k`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constraints {
+    size: usize,
+    fixed: Vec<(usize, usize)>,
+    forbidden: Vec<(usize, usize)>,
+    /// `allowed[i * size + k]` tells whether facility `i` may be placed at location `k`.
+    allowed: Vec<bool>,
+}
+
+impl Constraints {
+    /// No constraints on permutations of `size` locations.
+    pub fn new(size: usize) -> Constraints {
+        Constraints {
+            size,
+            fixed: Vec::new(),
+            forbidden: Vec::new(),
+            allowed: vec![true; size * size],
+        }
+    }
+
+    pub fn fix(&mut self, facility: usize, location: usize) {
+        self.check_pair(facility, location);
+        self.fixed.push((facility, location));
+        for other in (0..self.size).filter(|&other| other != location) {
+            self.allowed[facility * self.size + other] = false;
+        }
+        for other in (0..self.size).filter(|&other| other != facility) {
+            self.allowed[other * self.size + location] = false;
+        }
+    }
+
+    pub fn forbid(&mut self, facility: usize, location: usize) {
+        self.check_pair(facility, location);
+        self.forbidden.push((facility, location));
+        self.allowed[facility * self.size + location] = false;
+    }
+
+    fn check_pair(&self, facility: usize, location: usize) {
+        assert!(
+            facility < self.size && location < self.size,
+            "Facility {} or location {} out of range",
+            facility,
+            location
+        );
+    }
+
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get_fixed(&self) -> &[(usize, usize)] {
+        &self.fixed
+    }
+
+    pub fn get_forbidden(&self) -> &[(usize, usize)] {
+        &self.forbidden
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fixed.is_empty() && self.forbidden.is_empty()
+    }
+
+    pub fn allows(&self, facility: usize, location: usize) -> bool {
+        self.allowed[facility * self.size + location]
+    }
+
+    /// The first facility of `perm` placed where it may not be.
+    pub fn check(&self, perm: &[usize]) -> Result<(), ConstraintError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        match perm.iter().enumerate().find(|&(facility, &location)| !self.allows(facility, location)) {
+            Some((facility, &location)) => Err(ConstraintError::Violated { facility, location }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_feasible(&self, perm: &[usize]) -> bool {
+        self.check(perm).is_ok()
+    }
+
+    /// Moves the facilities of `perm` that break a constraint, keeping the allowed
+    /// assignments where possible. The feasible permutation is found as a perfect matching of
+    /// facilities and allowed locations, grown by augmenting paths from the allowed pairs of
+    /// `perm`; fails when no feasible permutation exists.
+    pub fn repair(&self, perm: &mut [usize]) -> Result<(), ConstraintError> {
+        if self.is_feasible(perm) {
+            return Ok(());
+        }
+        let mut facility_at: Vec<Option<usize>> = vec![None; self.size];
+        for (facility, &location) in perm.iter().enumerate() {
+            if self.allows(facility, location) && facility_at[location].is_none() {
+                facility_at[location] = Some(facility);
+            }
+        }
+        let mut placed = vec![false; self.size];
+        facility_at.iter().flatten().for_each(|&facility| placed[facility] = true);
+
+        for facility in (0..self.size).filter(|&facility| !placed[facility]) {
+            let mut visited = vec![false; self.size];
+            if !self.augment(facility, &mut facility_at, &mut visited) {
+                return Err(ConstraintError::Unsatisfiable);
+            }
+        }
+        for (location, facility) in facility_at.into_iter().enumerate() {
+            perm[facility.expect("Every location should be matched")] = location;
+        }
+        Ok(())
+    }
+
+    /// Looks for an augmenting path from `facility`, Kuhn's algorithm.
+    fn augment(&self, facility: usize, facility_at: &mut [Option<usize>], visited: &mut [bool]) -> bool {
+        for location in 0..self.size {
+            if !self.allows(facility, location) || visited[location] {
+                continue;
+            }
+            visited[location] = true;
+            let free = match facility_at[location] {
+                None => true,
+                Some(other) => self.augment(other, facility_at, visited),
+            };
+            if free {
+                facility_at[location] = Some(facility);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Random permutation repaired to satisfy the constraints.
+    pub fn random_permutation<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<Vec<usize>, ConstraintError> {
+        let mut perm = get_random_permutation(self.size, rng);
+        self.repair(&mut perm)?;
+        Ok(perm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn repair_satisfies_fixed_and_forbidden_pairs() {
+        let mut rng = StdRng::seed_from_u64(18);
+        let mut constraints = Constraints::new(6);
+        constraints.fix(2, 0);
+        constraints.forbid(0, 1);
+        constraints.forbid(0, 2);
+        (0..6).for_each(|facility| constraints.forbid(facility, 5));
+        constraints.check(&[0, 1, 2, 3, 4, 5]).unwrap_err();
+        assert!(constraints.random_permutation(&mut rng).is_err());
+
+        let mut constraints = Constraints::new(6);
+        constraints.fix(2, 0);
+        constraints.forbid(0, 1);
+        (1..6).for_each(|facility| constraints.forbid(facility, 5));
+        for _ in 0..50 {
+            let perm = constraints.random_permutation(&mut rng).unwrap();
+            assert!(constraints.is_feasible(&perm));
+            assert_eq!((perm[2], perm[0]), (0, 5));
+        }
+        assert_eq!(
+            Err(ConstraintError::Violated { facility: 0, location: 1 }),
+            constraints.check(&[1, 5, 0, 2, 3, 4])
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Debug, Display},
+    iter::Sum,
+    ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign},
+    str::FromStr,
+};
+
+/// Number type of the flow and distance matrices.
+///
+/// Products, sums and deltas are computed in the wider `Wide` type, which is as narrow as
+/// possible for fast kernels: `i64` for `usize` and `i32` costs. Instances check that their
+/// costs are small enough for every total and delta to fit into `Wide` (see `fits_wide`), so
+/// evaluating a move never overflows; only converting a total cost back with `narrow` is checked.
+pub trait Cost: Copy + PartialOrd + Default + Debug + Display + FromStr + Send + Sync + 'static {
+    type Wide: Copy
+        + PartialOrd
+        + Default
+        + Debug
+        + Display
+        + Add<Output = Self::Wide>
+        + Sub<Output = Self::Wide>
+        + Mul<Output = Self::Wide>
+        + Neg<Output = Self::Wide>
+        + AddAssign
+        + SubAssign
+        + Sum
+        + Send
+        + Sync;
+
+    /// Largest magnitude `Wide` holds, as a float.
+    const WIDE_MAX: f64;
+
+    fn widen(self) -> Self::Wide;
+
+    /// Converts a widened value back; fails when it does not fit into `Self`.
+    fn narrow(wide: Self::Wide) -> Result<Self, CostOverflow>;
+
+    fn to_f64(self) -> f64;
+
+    fn wide_to_f64(wide: Self::Wide) -> f64;
+
+    /// Converts a count such as a number of facilities, or a cost drawn by a generator.
+    fn from_usize(value: usize) -> Self;
+
+    /// Smallest cost at least `value`, saturating at the limits of the type; rounds real
+    /// lower bounds up to attainable costs.
+    fn ceil_from_f64(value: f64) -> Self;
+}
+
+/// A total cost that does not fit into the cost type of its instance.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CostOverflow {
+    pub cost: String,
+    pub cost_type: &'static str,
+}
+
+impl fmt::Display for CostOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cost {} does not fit into {}", self.cost, self.cost_type)
+    }
+}
+
+impl std::error::Error for CostOverflow {}
+
+macro_rules! integer_cost {
+    ($($cost:ty => $wide:ty),*) => {
+        $(
+            impl Cost for $cost {
+                type Wide = $wide;
+
+                const WIDE_MAX: f64 = <$wide>::MAX as f64;
+
+                fn widen(self) -> $wide {
+                    self as $wide
+                }
+
+                fn narrow(wide: $wide) -> Result<Self, CostOverflow> {
+                    <$cost>::try_from(wide).map_err(|_| CostOverflow {
+                        cost: wide.to_string(),
+                        cost_type: stringify!($cost),
+                    })
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn wide_to_f64(wide: $wide) -> f64 {
+                    wide as f64
+                }
+
+                fn from_usize(value: usize) -> $cost {
+                    value as $cost
+                }
+
+                fn ceil_from_f64(value: f64) -> $cost {
+                    value.ceil() as $cost
+                }
+            }
+        )*
+    };
+}
+
+integer_cost!(usize => i64, i32 => i64, u64 => i128, i64 => i128);
+
+impl Cost for f64 {
+    type Wide = f64;
+
+    const WIDE_MAX: f64 = f64::MAX;
+
+    fn widen(self) -> f64 {
+        self
+    }
+
+    fn narrow(wide: f64) -> Result<Self, CostOverflow> {
+        Ok(wide)
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn wide_to_f64(wide: f64) -> f64 {
+        wide
+    }
+
+    fn from_usize(value: usize) -> f64 {
+        value as f64
+    }
+
+    fn ceil_from_f64(value: f64) -> f64 {
+        value
+    }
+}
+
+/// Whether the totals and deltas of an instance of `size` facilities fit into `C::Wide`, when
+/// every product of a flow and a distance, or every four-index cost, is at most `max_term` in
+/// magnitude. A total sums `size * size` terms; deltas sum fewer terms of at most four times
+/// the magnitude, so the bound allows for four times the total.
+pub fn fits_wide<C: Cost>(size: usize, max_term: f64) -> bool {
+    4.0 * (size as f64).powi(2) * max_term < C::WIDE_MAX
+}
+
+/// Largest magnitude among `values`, as a float.
+pub fn magnitude<'a, C: Cost + 'a>(values: impl IntoIterator<Item = &'a C>) -> f64 {
+    values.into_iter().map(|value| value.to_f64().abs()).fold(0.0, f64::max)
+}
+
+/// Total order for sorting costs; panics on NaN.
+pub fn cmp_costs<T: PartialOrd>(first: &T, second: &T) -> Ordering {
+    first.partial_cmp(second).expect("Costs should not be NaN")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widened_products_do_not_overflow() {
+        let large = i32::MAX as usize;
+        assert_eq!(large.widen() * large.widen(), (i32::MAX as i64).pow(2));
+        assert_eq!(usize::narrow(large.widen()), Ok(large));
+        assert!(fits_wide::<usize>(1_000, 1e12));
+        assert!(!fits_wide::<usize>(1_000, (large * large) as f64));
+        assert!(!fits_wide::<u64>(2, (u64::MAX as f64).powi(2)));
+    }
+
+    #[test]
+    fn narrowing_checks_the_range() {
+        assert!(i64::narrow(i64::MAX as i128 + 1).is_err());
+        assert!(usize::narrow(-1).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+//! Pool of worker threads running independent tasks, such as the runs of an experiment.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Runs tasks on `workers` threads. Results are handed over in the order of the tasks, one
+/// at a time, so they can be written to shared files as soon as all earlier ones are done.
+pub struct ParallelExecutor {
+    workers: usize,
+}
+
+/// Results waiting for the ones of earlier tasks.
+struct Ordered<R, F> {
+    next: usize,
+    pending: BTreeMap<usize, R>,
+    collect: F,
+}
+
+impl ParallelExecutor {
+    /// `workers` threads, or one per available core when it is zero.
+    pub fn new(workers: usize) -> ParallelExecutor {
+        let workers = match workers {
+            0 => thread::available_parallelism().map_or(1, |cores| cores.get()),
+            workers => workers,
+        };
+        ParallelExecutor { workers }
+    }
+
+    pub fn get_workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Runs `run` on every task and passes the results to `collect` in the order of `tasks`.
+    pub fn execute<T, R, F>(&self, tasks: &[T], run: impl Fn(&T) -> R + Sync, collect: F)
+    where
+        T: Sync,
+        R: Send,
+        F: FnMut(&T, R) + Send,
+    {
+        let next_task = AtomicUsize::new(0);
+        let ordered = Mutex::new(Ordered {
+            next: 0,
+            pending: BTreeMap::new(),
+            collect,
+        });
+
+        thread::scope(|scope| {
+            for _ in 0..self.workers.min(tasks.len()) {
+                scope.spawn(|| loop {
+                    let index = next_task.fetch_add(1, Ordering::Relaxed);
+                    let Some(task) = tasks.get(index) else { break };
+                    let result = run(task);
+
+                    let mut guard = ordered.lock().expect("A worker panicked while collecting results");
+                    let ordered = &mut *guard;
+                    ordered.pending.insert(index, result);
+                    while let Some(result) = ordered.pending.remove(&ordered.next) {
+                        (ordered.collect)(&tasks[ordered.next], result);
+                        ordered.next += 1;
+                    }
+                });
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generator::{li_pardalos, LipaKind},
+        measure_run,
+        solver::local_search::greedy::GreedySolver,
+    };
+
+    #[test]
+    fn results_are_collected_in_task_order() {
+        let tasks: Vec<u64> = (0..40).collect();
+        let mut collected = Vec::new();
+        ParallelExecutor::new(4).execute(
+            &tasks,
+            |&task| {
+                thread::sleep(std::time::Duration::from_micros((task * 7919) % 13 * 100));
+                task * task
+            },
+            |&task, result| collected.push((task, result)),
+        );
+        assert_eq!(tasks.iter().map(|&task| (task, task * task)).collect::<Vec<_>>(), collected);
+    }
+
+    #[test]
+    fn parallel_runs_match_serial_ones() {
+        let instance = li_pardalos(12, LipaKind::B, 23);
+        let seeds: Vec<u64> = (0..16).collect();
+        let costs = |workers: usize| {
+            let mut costs = Vec::new();
+            ParallelExecutor::new(workers).execute(
+                &seeds,
+                |&seed| measure_run(&mut GreedySolver::new(&instance, u128::MAX), &instance, "lipa12b", seed, 0),
+                |_, metrics| costs.push((metrics.seed, metrics.cost, metrics.permutation)),
+            );
+            costs
+        };
+        assert_eq!(costs(1), costs(4));
+    }
+}
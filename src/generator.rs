@@ -0,0 +1,164 @@
+//! Seeded generators of synthetic instances, with a known optimum where the construction
+//! provides one. The same size and seed always give the same instance.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::{get_random_permutation, instance::Instance};
+
+/// Entry ranges of Li–Pardalos instances.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LipaKind {
+    /// Flows and distances from `1..=10`, like lipa*a.
+    A,
+    /// Flows from `1..=n` and distances from `1..=n^2`, like lipa*b.
+    B,
+}
+
+/// Asymmetric instance whose optimal permutation is planted, in the manner of Li and Pardalos.
+///
+/// Every row of the distances holds the same values, arranged in the opposite order of the
+/// flows of the facility at that location under the planted permutation. Each facility's
+/// flows then meet the smallest scalar product with its distances there, which is the same
+/// at every location, so the planted permutation attains the Gilmore–Lawler bound.
+pub fn li_pardalos(size: usize, kind: LipaKind, seed: u64) -> Instance {
+    assert!(size > 0, "Instance should have at least one facility");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (max_flow, max_distance) = match kind {
+        LipaKind::A => (10, 10),
+        LipaKind::B => (size, size * size),
+    };
+    let flows = random_matrix(size, &mut rng, |rng| rng.gen_range(1..=max_flow));
+    let mut values: Vec<usize> = (1..size).map(|_| rng.gen_range(1..=max_distance)).collect();
+    values.sort_unstable_by(|a, b| b.cmp(a));
+
+    let optimum = get_random_permutation(size, &mut rng);
+    let mut distances = vec![vec![0; size]; size];
+    for i in 0..size {
+        let mut others: Vec<usize> = (0..size).filter(|&j| j != i).collect();
+        others.sort_by_key(|&j| flows[i][j]);
+        for (&j, &value) in others.iter().zip(&values) {
+            distances[optimum[i]][optimum[j]] = value;
+        }
+    }
+
+    with_optimum(flows, distances, optimum)
+}
+
+/// Taillard's tai*a: symmetric flows and distances drawn uniformly from `0..100`.
+pub fn taillard_a(size: usize, seed: u64) -> Instance {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let flows = symmetric_matrix(size, &mut rng, |rng| rng.gen_range(0..100));
+    let distances = symmetric_matrix(size, &mut rng, |rng| rng.gen_range(0..100));
+    Instance::new(flows, distances, 0, vec![])
+}
+
+/// Taillard's structured tai*b: rounded Euclidean distances between locations scattered in
+/// clusters, and asymmetric flows with a heavy tail, mostly small with a few up to 999.
+pub fn taillard_b(size: usize, seed: u64) -> Instance {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let clusters = (size as f64).sqrt().ceil().max(1.0) as usize;
+    let centres: Vec<(f64, f64, f64)> = (0..clusters)
+        .map(|_| (rng.gen_range(0.0..100.0), rng.gen_range(0.0..100.0), rng.gen_range(1.0..10.0)))
+        .collect();
+    let points: Vec<(f64, f64)> = (0..size)
+        .map(|_| {
+            let (x, y, radius) = centres[rng.gen_range(0..clusters)];
+            let (angle, offset) = (rng.gen_range(0.0..std::f64::consts::TAU), radius * rng.gen::<f64>().sqrt());
+            (x + offset * angle.cos(), y + offset * angle.sin())
+        })
+        .collect();
+    let distances = (0..size)
+        .map(|i| {
+            let (x, y) = points[i];
+            (0..size).map(|j| (x - points[j].0).hypot(y - points[j].1).round() as usize).collect()
+        })
+        .collect();
+
+    let flows = random_matrix(size, &mut rng, |rng| {
+        let exponent = 3.0 * rng.gen::<f64>().powi(2);
+        10f64.powf(exponent) as usize - 1
+    });
+    Instance::new(flows, distances, 0, vec![])
+}
+
+/// Nugent-style instance on a `rows x columns` grid of locations: Manhattan distances and
+/// symmetric flows from `1..=10`, half of them zero.
+pub fn nugent(rows: usize, columns: usize, seed: u64) -> Instance {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let size = rows * columns;
+    let cell = |k: usize| (k / columns, k % columns);
+    let distances = (0..size)
+        .map(|k| {
+            let (row, column) = cell(k);
+            (0..size).map(|l| row.abs_diff(cell(l).0) + column.abs_diff(cell(l).1)).collect()
+        })
+        .collect();
+    let flows = symmetric_matrix(size, &mut rng, |rng| if rng.gen_bool(0.5) { rng.gen_range(1..=10) } else { 0 });
+    Instance::new(flows, distances, 0, vec![])
+}
+
+/// Matrix with a zero diagonal and the other entries drawn by `entry`.
+fn random_matrix(size: usize, rng: &mut StdRng, mut entry: impl FnMut(&mut StdRng) -> usize) -> Vec<Vec<usize>> {
+    (0..size)
+        .map(|i| (0..size).map(|j| if i == j { 0 } else { entry(rng) }).collect())
+        .collect()
+}
+
+fn symmetric_matrix(size: usize, rng: &mut StdRng, entry: impl FnMut(&mut StdRng) -> usize) -> Vec<Vec<usize>> {
+    let upper = random_matrix(size, rng, entry);
+    (0..size)
+        .map(|i| (0..size).map(|j| upper[i.min(j)][i.max(j)]).collect())
+        .collect()
+}
+
+fn with_optimum(flows: Vec<Vec<usize>>, distances: Vec<Vec<usize>>, optimum: Vec<usize>) -> Instance {
+    let mut instance = Instance::new(flows, distances, 0, optimum);
+    instance.optimal_cost = instance
+        .evaluate(&instance.optimal_permutation)
+        .expect("Generated instances should be unconstrained");
+    instance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bounds::gilmore_lawler,
+        solver::{branch_and_bound::BranchAndBoundSolver, Solver},
+    };
+
+    #[test]
+    fn li_pardalos_optimum_is_planted() {
+        let mut rng = StdRng::seed_from_u64(21);
+        for (seed, kind) in [(1, LipaKind::A), (2, LipaKind::B), (3, LipaKind::A), (4, LipaKind::B)] {
+            let instance = li_pardalos(7, kind, seed);
+            assert_eq!(instance.optimal_cost as i64, gilmore_lawler(&instance));
+
+            let mut solver = BranchAndBoundSolver::new(&instance, u128::MAX);
+            let solution = solver.solve(get_random_permutation(7, &mut rng), &mut rng).unwrap();
+            assert_eq!(instance.optimal_cost, instance.evaluate(&solution.permutation).unwrap());
+        }
+    }
+
+    #[test]
+    fn generators_are_seeded() {
+        let generators: [fn(u64) -> Instance; 5] = [
+            |seed| li_pardalos(12, LipaKind::A, seed),
+            |seed| li_pardalos(12, LipaKind::B, seed),
+            |seed| taillard_a(12, seed),
+            |seed| taillard_b(12, seed),
+            |seed| nugent(3, 4, seed),
+        ];
+        for generate in generators {
+            let (first, second, other) = (generate(5), generate(5), generate(6));
+            assert_eq!((&first.matrix_a, &first.matrix_b), (&second.matrix_a, &second.matrix_b));
+            assert_eq!(first.optimal_permutation, second.optimal_permutation);
+            assert_ne!(first.matrix_a, other.matrix_a);
+            assert_eq!(12, first.get_size());
+        }
+        assert_eq!(3 + 2, nugent(3, 4, 0).matrix_b[0][11]);
+        assert!(taillard_a(12, 0).is_symmetric());
+    }
+}
@@ -1,11 +1,23 @@
-use std::error::Error;
-use crate::{io, Metrics};
+use crate::{
+    cost::{fits_wide, magnitude, Cost},
+    matrix::LawlerCosts,
+    Metrics,
+};
 
-use super::instance::Instance;
+use super::instance::{Formulation, Instance};
+use parse::{ParseError, ParseErrorKind, Source, Tokens};
+use std::collections::HashSet;
 use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, ErrorKind};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 
+pub mod config;
 pub mod experiments;
+pub mod parse;
+
+type Matrix<C = usize> = Vec<Vec<C>>;
+/// Flow and distance matrices, and the installation costs of a linear term if present.
+type DatMatrices<C> = (Matrix<C>, Matrix<C>, Option<Matrix<C>>);
 
 pub struct InstanceReader<'a> {
     dir: &'a str,
@@ -16,129 +28,249 @@ impl<'q> InstanceReader<'q> {
         InstanceReader { dir }
     }
 
-    pub fn read_instance(&self, filename: &str) -> std::io::Result<Instance> {
-        let (_size, optimal_cost, optimal_perm) = self.read_optimal_solution(filename).unwrap();
-        let (matrix_a, matrix_b) = self.read_instance_dat_file(filename).unwrap();
-
-        Ok(Instance::new(matrix_a, matrix_b, optimal_cost, optimal_perm))
+    fn path(&self, filename: &str, extension: &str) -> PathBuf {
+        Path::new(self.dir).join(format!("{}.{}", filename, extension))
     }
 
-    pub fn read_instance_dat_file(&self, filename: &str) -> std::io::Result<(Vec<Vec<usize>>, Vec<Vec<usize>>)> {
-        let instance_file = File::open(format!("{}/{}.dat", self.dir, filename))?;
-        let instance_reader = BufReader::new(instance_file);
-        let mut line_iter = instance_reader.lines().peekable();
-        let matrix_size: usize;
+    /// Reads `<filename>.dat` and `<filename>.sln` with matrix entries and costs of type `C`.
+    /// A third matrix in the `.dat` file holds the installation costs of a linear term. A
+    /// first line `n m` instead of a single size declares `n` facilities and `m` locations,
+    /// followed by `n x n` flows, `m x m` distances and optionally `n x m` installation costs.
+    /// Without a `.sln` file the optimal permutation is empty and the optimal cost zero.
+    pub fn read_instance<C: Cost>(&self, filename: &str) -> Result<Instance<C>, ParseError> {
+        let dat = Source::read(self.path(filename, "dat"))?;
+        let solution = self.read_solution_source(filename)?;
+        parse_instance(&dat, solution.as_ref())
+    }
 
-        loop {
-            if let Some(Ok(line)) = line_iter.next() {
-                if line.is_empty() {
-                    continue;
-                }
-                matrix_size = line.trim().parse().expect("First number should be matrix size");
-                break;
-            }
-        }
+    /// Reads a Lawler instance: `<filename>.dat` holds the size `n` followed by `n^2` blocks
+    /// of `n x n` numbers, the block of facilities `(i, j)` listing `d[i][j][k][l]` with rows
+    /// `k`, in row-major order of `(i, j)`. The `.sln` file is the same as for other instances.
+    pub fn read_lawler_instance<C: Cost>(&self, filename: &str) -> Result<Instance<C>, ParseError> {
+        let dat = Source::read(self.path(filename, "dat"))?;
+        let solution = self.read_solution_source(filename)?;
+        parse_lawler_instance(&dat, solution.as_ref())
+    }
 
-        self.skip_empty_lines(&mut line_iter);
-        let matrix_a = self.read_matrix(matrix_size, &mut line_iter)?;
+    /// Reads the flow and distance matrices of `<filename>.dat`, and the matrix of installation
+    /// costs when the file has a third one.
+    pub fn read_instance_dat_file<C: Cost>(&self, filename: &str) -> Result<DatMatrices<C>, ParseError> {
+        parse_dat(&Source::read(self.path(filename, "dat"))?)
+    }
 
-        self.skip_empty_lines(&mut line_iter);
-        let matrix_b = self.read_matrix(matrix_size, &mut line_iter)?;
+    /// Size, cost and 0-based permutation of `<filename>.sln`.
+    pub fn read_optimal_solution<C: Cost>(&self, filename: &str) -> Result<(usize, C, Vec<usize>), ParseError> {
+        parse_solution(&Source::read(self.path(filename, "sln"))?, None)
+    }
 
-        Ok((matrix_a, matrix_b))
+    fn read_solution_source(&self, filename: &str) -> Result<Option<Source>, ParseError> {
+        match Source::read(self.path(filename, "sln")) {
+            Ok(source) => Ok(Some(source)),
+            Err(error) if error.is_not_found() => Ok(None),
+            Err(error) => Err(error),
+        }
     }
+}
+
+pub struct InstanceWriter<'a> {
+    dir: &'a str,
+}
 
-    pub fn read_optimal_solution(&self, filename: &str) -> std::io::Result<(usize, usize, Vec<usize>)> {
-        let file = File::open(format!("{}/{}.sln", self.dir, filename))?;
-        let reader = BufReader::new(file);
-        let mut iterator = reader.lines().peekable();
-        let mut size_and_cost_read = false;
-        let (mut size, mut cost): (usize, usize) = (0, 0);
-        let mut perm_elements_read = 0;
+impl<'q> InstanceWriter<'q> {
+    pub fn new(dir: &'q str) -> Self {
+        InstanceWriter { dir }
+    }
 
+    fn create(&self, filename: &str, extension: &str) -> std::io::Result<BufWriter<File>> {
+        std::fs::create_dir_all(self.dir)?;
+        Ok(BufWriter::new(File::create(Path::new(self.dir).join(format!("{}.{}", filename, extension)))?))
+    }
 
-        while !size_and_cost_read {
-            if let (Some(Ok(line))) = iterator.next() {
-                if !line.is_empty() {
-                    let mut numbers_iter = line.trim().split_whitespace();
-                    size = numbers_iter.next()
-                        .ok_or(ErrorKind::UnexpectedEof)?
-                        .parse::<usize>()
-                        .map_err(|_| io::ErrorKind::InvalidData)?;
+    /// Writes `instance` to `<filename>.dat` in the format `InstanceReader` reads it from, and
+    /// its optimal solution to `<filename>.sln`, removing a stale one if it has none. Lawler
+    /// instances are written as blocks for `read_lawler_instance`.
+    pub fn write_instance<C: Cost>(&self, filename: &str, instance: &Instance<C>) -> std::io::Result<()> {
+        let mut out = self.create(filename, "dat")?;
+        let (facilities, size) = (instance.get_facility_count(), instance.get_size());
 
-                    cost = numbers_iter.next()
-                        .ok_or(ErrorKind::UnexpectedEof)?
-                        .parse::<usize>()
-                        .map_err(|_| io::ErrorKind::UnexpectedEof)?;
-                    size_and_cost_read = true;
+        match instance.get_formulation() {
+            Formulation::Lawler(costs) => {
+                writeln!(out, "{}", size)?;
+                for i in 0..size {
+                    for j in 0..size {
+                        write_rows(&mut out, costs.block(i, j).chunks(size))?;
+                    }
+                }
+            }
+            formulation => {
+                if facilities == size {
+                    writeln!(out, "{}", size)?;
+                } else {
+                    writeln!(out, "{} {}", facilities, size)?;
+                }
+                write_rows(&mut out, instance.matrix_a.rows().take(facilities).map(|row| &row[..facilities]))?;
+                write_rows(&mut out, instance.matrix_b.rows())?;
+                if let Formulation::Linear(costs) = formulation {
+                    write_rows(&mut out, costs.rows().take(facilities))?;
                 }
-            } else {
-                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Empty file"));
             }
         }
+        out.flush()?;
 
-        let mut optimal_perm: Vec<usize> = Vec::with_capacity(size);
-        while perm_elements_read < size {
-            let Some(Ok(line)) = iterator.next() else {continue};
-            if !line.is_empty() {
-                let elements: Vec<usize> = line
-                    .trim()
-                    .split_whitespace()
-                    .map(|n| (n.parse::<isize>().unwrap() - 1) as usize)
-                    .collect();
-
-                optimal_perm.extend_from_slice(&elements);
-                perm_elements_read += elements.len();
-            } else {}
+        if instance.optimal_permutation.is_empty() {
+            return match std::fs::remove_file(Path::new(self.dir).join(format!("{}.sln", filename))) {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error),
+                _ => Ok(()),
+            };
         }
+        self.write_solution(filename, instance.optimal_cost, instance.assignment(&instance.optimal_permutation))
+    }
 
-        Ok((size, cost, optimal_perm))
+    /// Writes `<filename>.sln` with the size and cost of a solution followed by the 1-based
+    /// locations of its facilities.
+    pub fn write_solution<C: Cost>(&self, filename: &str, cost: C, assignment: &[usize]) -> std::io::Result<()> {
+        let mut out = self.create(filename, "sln")?;
+        writeln!(out, "{} {}", assignment.len(), cost)?;
+        let locations: Vec<String> = assignment.iter().map(|location| (location + 1).to_string()).collect();
+        writeln!(out, "{}", locations.join(" "))?;
+        out.flush()
     }
+}
 
-    fn skip_empty_lines(
-        &self,
-        line_iter: &mut std::iter::Peekable<std::io::Lines<BufReader<File>>>,
-    ) {
-        while let Some(Ok(line)) = line_iter.peek() {
-            if line.trim().is_empty() {
-                line_iter.next();
-            } else {
-                break;
-            }
+/// Writes a matrix after an empty line, one row per line.
+fn write_rows<'a, C: Cost>(out: &mut impl Write, rows: impl Iterator<Item = &'a [C]>) -> std::io::Result<()> {
+    writeln!(out)?;
+    for row in rows {
+        let entries: Vec<String> = row.iter().map(ToString::to_string).collect();
+        writeln!(out, "{}", entries.join(" "))?;
+    }
+    Ok(())
+}
+
+fn parse_instance<C: Cost>(dat: &Source, solution: Option<&Source>) -> Result<Instance<C>, ParseError> {
+    let (matrix_a, matrix_b, linear_costs) = parse_dat(dat)?;
+    let quadratic = magnitude(matrix_a.iter().flatten()) * magnitude(matrix_b.iter().flatten());
+    let linear = linear_costs.as_ref().map_or(0.0, |costs| magnitude(costs.iter().flatten()));
+    check_fits_wide::<C>(dat, matrix_b.len(), quadratic + linear)?;
+    let (optimal_cost, optimal_perm) = match solution {
+        Some(solution) => {
+            let (_size, cost, perm) = parse_solution(solution, Some((matrix_a.len(), matrix_b.len())))?;
+            (cost, perm)
         }
+        None => (C::default(), Vec::new()),
+    };
+
+    Ok(match linear_costs {
+        Some(linear_costs) => Instance::with_linear_costs(matrix_a, matrix_b, linear_costs, optimal_cost, optimal_perm),
+        None => Instance::new(matrix_a, matrix_b, optimal_cost, optimal_perm),
+    })
+}
+
+fn parse_lawler_instance<C: Cost>(dat: &Source, solution: Option<&Source>) -> Result<Instance<C>, ParseError> {
+    let mut tokens = Tokens::new(dat);
+    let size = match tokens.line_numbers::<usize>("size")?[..] {
+        [size] => size,
+        _ => return Err(tokens.error(ParseErrorKind::InvalidSize { reason: "first line should hold one size" })),
+    };
+    check_sizes(&tokens, size, size)?;
+    let block_count = size
+        .checked_mul(size)
+        .ok_or_else(|| tokens.error(ParseErrorKind::InvalidSize { reason: "instance is too large" }))?;
+
+    let mut blocks = Vec::new();
+    for _ in 0..block_count {
+        blocks.push(tokens.matrix(size, size)?);
     }
+    tokens.expect_end()?;
+    check_fits_wide::<C>(dat, size, magnitude(blocks.iter().flatten().flatten()))?;
 
-    fn read_matrix(
-        &self,
-        size: usize,
-        line_iter: &mut std::iter::Peekable<std::io::Lines<BufReader<File>>>,
-    ) -> std::io::Result<Vec<Vec<usize>>> {
-        let mut matrix_a: Vec<Vec<usize>> = Vec::with_capacity(size);
-        let mut total_numbers_read = 0;
-        while total_numbers_read < size * size {
-            let mut row = Vec::with_capacity(size);
-            while row.len() < size {
-                if let Some(Ok(line)) = line_iter.next() {
-                    let numbers_in_line: Vec<usize> = line
-                        .trim()
-                        .split_whitespace()
-                        .map(|num_str| num_str.parse().unwrap())
-                        .collect();
-                    row.extend_from_slice(&numbers_in_line);
-                    total_numbers_read += numbers_in_line.len();
-                } else {
-                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Empty file"));
-                }
-            }
-            matrix_a.push(row);
+    let (optimal_cost, optimal_perm) = match solution {
+        Some(solution) => {
+            let (_size, cost, perm) = parse_solution(solution, Some((size, size)))?;
+            (cost, perm)
         }
-        Ok(matrix_a)
+        None => (C::default(), Vec::new()),
+    };
+    Ok(Instance::from_lawler_costs(LawlerCosts::from_blocks(size, blocks), optimal_cost, optimal_perm))
+}
+
+fn parse_dat<C: Cost>(source: &Source) -> Result<DatMatrices<C>, ParseError> {
+    let mut tokens = Tokens::new(source);
+    let (facilities, locations) = match tokens.line_numbers::<usize>("size")?[..] {
+        [size] => (size, size),
+        [facilities, locations] => (facilities, locations),
+        _ => return Err(tokens.error(ParseErrorKind::InvalidSize { reason: "first line should hold one or two sizes" })),
+    };
+    check_sizes(&tokens, facilities, locations)?;
+
+    let matrix_a = tokens.matrix(facilities, facilities)?;
+    let matrix_b = tokens.matrix(locations, locations)?;
+    let linear_costs = if tokens.is_at_end() { None } else { Some(tokens.matrix(facilities, locations)?) };
+    tokens.expect_end()?;
+
+    Ok((matrix_a, matrix_b, linear_costs))
+}
+
+fn check_sizes(tokens: &Tokens, facilities: usize, locations: usize) -> Result<(), ParseError> {
+    let reason = match (facilities, locations) {
+        (0, _) => "sizes should be positive",
+        (facilities, locations) if facilities > locations => "more facilities than locations",
+        _ => return Ok(()),
+    };
+    Err(tokens.error(ParseErrorKind::InvalidSize { reason }))
+}
+
+/// Error unless `Instance` accepts `size` locations whose terms are at most `max_term` in
+/// magnitude.
+fn check_fits_wide<C: Cost>(source: &Source, size: usize, max_term: f64) -> Result<(), ParseError> {
+    if fits_wide::<C>(size, max_term) {
+        return Ok(());
     }
+    Err(ParseError {
+        file: source.path.clone(),
+        line: 0,
+        column: 0,
+        kind: ParseErrorKind::CostsTooLarge { cost_type: std::any::type_name::<C>() },
+    })
 }
 
-pub fn save_metrics_to_csv(
+/// Parses a solution: its size and cost followed by the 1-based locations of the facilities.
+/// With the `(facilities, locations)` of an instance, the size must match its facilities.
+fn parse_solution<C: Cost>(
+    source: &Source,
+    sizes: Option<(usize, usize)>,
+) -> Result<(usize, C, Vec<usize>), ParseError> {
+    let mut tokens = Tokens::new(source);
+    let size: usize = tokens.next_number("solution size")?;
+    let locations = match sizes {
+        Some((facilities, _)) if facilities != size => {
+            return Err(tokens.error(ParseErrorKind::SizeMismatch { expected: facilities, found: size }));
+        }
+        Some((_, locations)) => locations,
+        None => size,
+    };
+    let cost = tokens.next_cost()?;
+
+    let mut used = HashSet::new();
+    let mut perm = Vec::new();
+    for _ in 0..size {
+        let location: usize = tokens.next_number("location")?;
+        if location == 0 || location > locations {
+            return Err(tokens.error(ParseErrorKind::LocationOutOfRange { location, locations }));
+        }
+        if !used.insert(location) {
+            return Err(tokens.error(ParseErrorKind::DuplicateLocation { location }));
+        }
+        perm.push(location - 1);
+    }
+    tokens.expect_end()?;
+
+    Ok((size, cost, perm))
+}
+
+pub fn save_metrics_to_csv<C: Cost>(
     filename: &str,
-    metrics: &[Metrics],
+    metrics: &[Metrics<C>],
 ) -> Result<(), Box<dyn std::error::Error>> {
     let folder_path = std::path::Path::new(filename)
         .parent()
@@ -163,6 +295,9 @@ pub fn save_metrics_to_csv(
             "InitialCost",
             "TimeLimit",
             "SlnDistance",
+            "Seed",
+            "LowerBound",
+            "Gap",
         ])?;
     }
 
@@ -171,13 +306,16 @@ pub fn save_metrics_to_csv(
         writer.serialize((
             &metric.instance_name,
             metric.duration,
-            metric.cost,
+            metric.cost.to_string(),
             metric.evaluated_solutions,
             metric.solution_changes,
-            metric.optimal_cost,
-            metric.initial_cost,
+            metric.optimal_cost.to_string(),
+            metric.initial_cost.to_string(),
             metric.time_limit,
             metric.solution_distance,
+            metric.seed,
+            metric.lower_bound.to_string(),
+            metric.gap,
         ))?;
     }
 
@@ -185,15 +323,226 @@ pub fn save_metrics_to_csv(
     Ok(())
 }
 
+/// Writes the convergence trace of a run to `filename`, one point per row.
+pub fn save_trace_to_csv<C: Cost>(filename: &str, metrics: &Metrics<C>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::Writer::from_writer(create_file(filename)?);
+    writer.write_record(["Time", "Evaluations", "CurrentCost", "BestCost"])?;
+    for point in &metrics.trace {
+        writer.serialize((point.elapsed, point.evaluations, point.current_cost, point.best_cost))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the convergence trace of a run to `filename` as a JSON object with the instance,
+/// the seed and the points of the trace.
+pub fn save_trace_to_json<C: Cost>(filename: &str, metrics: &Metrics<C>) -> Result<(), Box<dyn std::error::Error>> {
+    let run = serde_json::json!({
+        "instance": metrics.instance_name,
+        "seed": metrics.seed,
+        "trace": metrics.trace,
+    });
+    let mut writer = BufWriter::new(create_file(filename)?);
+    serde_json::to_writer(&mut writer, &run)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Creates `filename` along with its missing parent directories.
+fn create_file(filename: &str) -> std::io::Result<File> {
+    if let Some(folder_path) = Path::new(filename).parent() {
+        std::fs::create_dir_all(folder_path)?;
+    }
+    File::create(filename)
+}
+
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
+    const TINY: &str = "3\n\n0 1 2\n1 0 3\n2 3 0\n\n0 5 2\n5 0 1\n2 1 0\n";
+
     #[test]
     fn should_read_file() {
-        let instance_reader = InstanceReader::new("qap/instances");
-        let instance = instance_reader.read_instance("chr12a.dat");
+        let dir = std::env::temp_dir().join("qap-should-read-file");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("tiny3.dat"), TINY).unwrap();
+        std::fs::write(dir.join("tiny3.sln"), "3 24\n1 2 3\n").unwrap();
+
+        let instance_reader = InstanceReader::new(dir.to_str().unwrap());
+        let instance: Instance = instance_reader.read_instance("tiny3").unwrap();
+
+        assert_eq!(3, instance.get_size());
+        assert_eq!(vec![0, 1, 2], instance.optimal_permutation);
+        assert_eq!(24, instance.evaluate(&instance.optimal_permutation).unwrap());
+    }
+
+    #[test]
+    fn should_read_linear_and_lawler_files() {
+        let dir = std::env::temp_dir().join("qap-should-read-linear-and-lawler-files");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("linear2.dat"), "2\n\n0 1\n2 0\n\n0 3\n4 0\n\n5 6\n7 8\n").unwrap();
+        std::fs::write(dir.join("linear2.sln"), "2 23\n2 1\n").unwrap();
+        std::fs::write(dir.join("lawler2.dat"), "2\n\n1 2\n3 4\n\n5 6\n7 8\n\n9 10\n11 12\n\n13 14\n15 16\n").unwrap();
+        std::fs::write(dir.join("lawler2.sln"), "2 34\n1 2\n").unwrap();
+
+        let instance_reader = InstanceReader::new(dir.to_str().unwrap());
+        let linear: Instance = instance_reader.read_instance("linear2").unwrap();
+        assert_eq!(Some(7), linear.get_linear_costs().map(|costs| costs[1][0]));
+        assert_eq!(23, linear.evaluate(&linear.optimal_permutation).unwrap());
+
+        let lawler: Instance = instance_reader.read_lawler_instance("lawler2").unwrap();
+        assert_eq!(1 + 6 + 11 + 16, lawler.evaluate(&[0, 1]).unwrap());
+        assert_eq!(34, lawler.evaluate(&lawler.optimal_permutation).unwrap());
+    }
+
+    #[test]
+    fn should_read_rectangular_file() {
+        let dir = std::env::temp_dir().join("qap-should-read-rectangular-file");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("rect2x3.dat"), "2 3\n\n0 2\n1 0\n\n0 1 4\n1 0 2\n4 2 0\n\n3 0 1\n2 2 0\n").unwrap();
+        std::fs::write(dir.join("rect2x3.sln"), "2 6\n2 3\n").unwrap();
+
+        let instance_reader = InstanceReader::new(dir.to_str().unwrap());
+        let instance: Instance = instance_reader.read_instance("rect2x3").unwrap();
 
-        assert_eq!(12, instance.unwrap().get_size());
+        assert_eq!((2, 3), (instance.get_facility_count(), instance.get_size()));
+        assert_eq!(vec![1, 2, 0], instance.optimal_permutation);
+        assert_eq!(6, instance.evaluate(&instance.optimal_permutation).unwrap());
+    }
+
+    #[test]
+    fn should_read_wrapped_rows() {
+        // Rows wrapped over several lines as in tai*, and rows sharing a line.
+        let wrapped = "3\n\n 0 1\n 2\n 1 0\n 3\n 2\n 3 0\n\n0 5 2 5 0 1\n2 1 0\n";
+        let (matrix_a, matrix_b, linear_costs) = parse_dat::<usize>(&Source::new("wrapped.dat", wrapped)).unwrap();
+        let (expected_a, expected_b, _) = parse_dat::<usize>(&Source::new("tiny3.dat", TINY)).unwrap();
+        assert_eq!((expected_a, expected_b), (matrix_a, matrix_b));
+        assert!(linear_costs.is_none());
+    }
+
+    #[test]
+    fn should_report_where_files_are_malformed() {
+        let dat = |text: &str| parse_dat::<usize>(&Source::new("bad.dat", text)).unwrap_err();
+        let position = |error: &ParseError| (error.line, error.column);
+
+        let error = dat("");
+        assert!(matches!(error.kind, ParseErrorKind::UnexpectedEof { .. }));
+        let error = dat("2\n\n0 1\n1 x\n\n0 1\n1 0\n");
+        assert!(matches!(&error.kind, ParseErrorKind::InvalidNumber { token, .. } if token == "x"));
+        assert_eq!((4, 3), position(&error));
+        let error = dat("2\n\n0 1\n1 0\n\n0 1\n1\n");
+        assert!(matches!(error.kind, ParseErrorKind::InvalidSize { .. }));
+        assert_eq!((6, 1), position(&error));
+        assert!(matches!(dat("100000000000000000\n0\n").kind, ParseErrorKind::InvalidSize { .. }));
+        let huge = |cost: &str| Source::new("huge.dat", format!("2\n\n0 {}\n1 0\n\n0 1\n1 0\n", cost));
+        let error = parse_instance::<usize>(&huge("9000000000000000000"), None).unwrap_err();
+        assert!(matches!(error.kind, ParseErrorKind::CostsTooLarge { cost_type: "usize" }));
+        let error = parse_instance::<f64>(&huge("1e308"), None).unwrap_err();
+        assert!(matches!(error.kind, ParseErrorKind::CostsTooLarge { cost_type: "f64" }));
+        assert!(matches!(dat("3 2\n").kind, ParseErrorKind::InvalidSize { .. }));
+        assert!(matches!(dat("0\n").kind, ParseErrorKind::InvalidSize { .. }));
+        assert!(matches!(dat("1\n0\n0\n1\n2\n").kind, ParseErrorKind::TrailingData));
+        assert_eq!("bad.dat:1:1: Unexpected end of file, expected size", dat("").to_string());
+
+        let solution = |text: &str| parse_solution::<usize>(&Source::new("bad.sln", text), Some((2, 3))).unwrap_err();
+        assert!(matches!(solution("2 7\n1").kind, ParseErrorKind::UnexpectedEof { .. }));
+        assert!(matches!(solution("3 7\n1 2 3").kind, ParseErrorKind::SizeMismatch { expected: 2, found: 3 }));
+        assert!(matches!(solution("2 7\n4 1").kind, ParseErrorKind::LocationOutOfRange { location: 4, .. }));
+        assert!(matches!(solution("2 7\n0 1").kind, ParseErrorKind::LocationOutOfRange { location: 0, .. }));
+        let error = solution("2 7\n3 3");
+        assert!(matches!(error.kind, ParseErrorKind::DuplicateLocation { location: 3 }));
+        assert_eq!((2, 3), position(&error));
+    }
+
+    #[test]
+    fn should_read_instance_without_solution() {
+        let dir = std::env::temp_dir().join("qap-should-read-instance-without-solution");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("nosln3.dat"), TINY).unwrap();
+        let _ = std::fs::remove_file(dir.join("nosln3.sln"));
+
+        let instance_reader = InstanceReader::new(dir.to_str().unwrap());
+        let instance: Instance = instance_reader.read_instance("nosln3").unwrap();
+        assert!(instance.optimal_permutation.is_empty());
+        assert!(instance_reader.read_optimal_solution::<usize>("nosln3").unwrap_err().is_not_found());
+        assert!(instance_reader.read_instance::<usize>("missing").unwrap_err().is_not_found());
+    }
+
+    #[test]
+    fn written_instances_read_back_unchanged() {
+        fn assert_round_trip<C: Cost + PartialEq>(instance: &Instance<C>, lawler: bool) {
+            let dir = std::env::temp_dir().join("qap-written-instances-read-back-unchanged");
+            let dir = dir.to_str().unwrap();
+            InstanceWriter::new(dir).write_instance("written", instance).unwrap();
+            let reader = InstanceReader::new(dir);
+            let read: Instance<C> = if lawler {
+                reader.read_lawler_instance("written").unwrap()
+            } else {
+                reader.read_instance("written").unwrap()
+            };
+            assert_eq!(instance.matrix_a, read.matrix_a);
+            assert_eq!(instance.matrix_b, read.matrix_b);
+            assert_eq!(instance.get_formulation(), read.get_formulation());
+            assert_eq!((instance.facilities, instance.size), (read.facilities, read.size));
+            assert_eq!(instance.optimal_cost, read.optimal_cost);
+            assert_eq!(instance.optimal_permutation, read.optimal_permutation);
+        }
+
+        let square: Instance = parse_instance(&Source::new("tiny3.dat", TINY), Some(&Source::new("tiny3.sln", "3 24\n1 2 3"))).unwrap();
+        assert_round_trip(&square, false);
+        let rectangular: Instance<i64> = Instance::with_linear_costs(
+            vec![vec![0, -2], vec![1, 0]],
+            vec![vec![0, 1, 4], vec![1, 0, 2], vec![4, 2, 0]],
+            vec![vec![3, 0, 1], vec![2, -2, 0]],
+            5,
+            vec![2, 0],
+        );
+        assert_round_trip(&rectangular, false);
+        let fractional: Instance<f64> = Instance::new(vec![vec![0.0, 0.1], vec![1e-7, 0.0]], vec![vec![0.0, 2.5], vec![1.0 / 3.0, 0.0]], 0.0, vec![]);
+        assert_round_trip(&fractional, false);
+        let lawler: Instance = Instance::from_lawler_costs(LawlerCosts::from_flat(2, (0..16).collect()), 34, vec![0, 1]);
+        assert_round_trip(&lawler, true);
+
+        let dir = std::env::temp_dir().join("qap-written-instances-read-back-unchanged");
+        InstanceWriter::new(dir.to_str().unwrap()).write_solution("best", 17, &[2, 0, 1]).unwrap();
+        assert_eq!("3 17\n3 1 2\n", std::fs::read_to_string(dir.join("best.sln")).unwrap());
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_files_never_panic(
+            size in prop::sample::select(vec!["", "2\n", "100000000000000000\n", "18446744073709551615\n"]),
+            dat in "[0-9 ,.ex\n-]{0,120}",
+            sln in "[0-9 x\n-]{0,30}",
+        ) {
+            let dat = format!("{}{}", size, dat);
+            let solution = Source::new("fuzz.sln", sln);
+            let _ = parse_instance::<usize>(&Source::new("fuzz.dat", dat.clone()), Some(&solution));
+            let _ = parse_instance::<f64>(&Source::new("fuzz.dat", dat.clone()), None);
+            let _ = parse_lawler_instance::<i64>(&Source::new("fuzz.dat", dat), Some(&solution));
+        }
+
+        #[test]
+        fn corrupted_files_never_panic(
+            cut in 0..TINY.len(),
+            replaced in 0..TINY.len(),
+            replacement in prop::sample::select(vec![
+                "0", "7", " ", "\n", "x", "-", ",", "100000000000000000", "9000000000000000000", "1e308",
+            ]),
+            sln in prop::sample::select(vec!["3 24\n1 2 3", "3 24\n1 2", "3 24\n1 2 2", "2 24\n1 2", ""]),
+        ) {
+            let mut corrupted: String = TINY.to_string();
+            corrupted.replace_range(replaced..=replaced, replacement);
+            let solution = Source::new("fuzz.sln", sln);
+            for text in [&TINY[..cut], &corrupted] {
+                let _ = parse_instance::<usize>(&Source::new("fuzz.dat", text), Some(&solution));
+                let _ = parse_instance::<f64>(&Source::new("fuzz.dat", text), None);
+                let _ = parse_lawler_instance::<i64>(&Source::new("fuzz.dat", text), None);
+            }
+            prop_assert!(parse_instance::<usize>(&Source::new("fuzz.dat", TINY), None).is_ok());
+        }
     }
 }
@@ -0,0 +1,572 @@
+//! Experiments described in a TOML or JSON file: the instances to solve, the solvers with
+//! their parameters, time and evaluation budgets, runs, seeds and the output directory.
+//!
+//! ```toml
+//! output_dir = "output/chr"
+//! time_limit = 2_500_000
+//! runs = 10
+//! seeds = [42]
+//! workers = 4
+//! tracing = { interval = 1_000_000 }
+//!
+//! [[instances]]
+//! name = "chr12a"
+//!
+//! [[instances]]
+//! name = "lipa30b"
+//! time_limit = 50_000_000
+//! generator = { kind = "li_pardalos", size = 30, variant = "b", seed = 7 }
+//!
+//! [[solvers]]
+//! type = "tabu_search"
+//! max_evaluations = 100_000
+//!
+//! [[solvers]]
+//! type = "simulated_annealing"
+//! label = "SA-fast"
+//! neighbourhood = "three_cycle"
+//! schedule = { kind = "geometric", alpha = 0.9 }
+//! epoch_length = 200
+//! ```
+
+use std::fmt;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{
+    cost::Cost,
+    generator::{self, LipaKind},
+    instance::Instance,
+    io::{parse::ParseError, save_trace_to_csv, save_trace_to_json, InstanceReader},
+    matrix::Matrix,
+    solver::{
+        ant_colony::{AntSystemConfig, MaxMinAntSystemSolver},
+        branch_and_bound::BranchAndBoundSolver,
+        genetic::{GeneticConfig, GeneticSolver},
+        grasp::{GraspConfig, GraspSolver},
+        heuristic_solver::HeuristicSolver,
+        iterated_local_search::{Acceptance, IteratedLocalSearchSolver, Perturbation},
+        linear_assignment::LinearAssignmentSolver,
+        local_search::{greedy::GreedySolver, steepest::SteepestSolver, LocalSearch},
+        neighbourhood::{Insertion, Neighbourhood, Relocation, Swap, ThreeCycle},
+        random_search::RandomSearchSolver,
+        random_walk::RandomWalkSolver,
+        simulated_annealing::{
+            cooling::{CoolingSchedule, Geometric, Linear, Logarithmic, LundyMees, Reheating},
+            AnnealingConfig, SimulatedAnnealingSolver,
+        },
+        tabu_search::TabuSearchSolver,
+        trace::Tracing,
+        variable_neighbourhood::VariableNeighbourhoodSolver,
+        Solver,
+    },
+    Metrics,
+};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    /// The file extension is neither `.toml` nor `.json`.
+    UnknownFormat(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "{}", error),
+            ConfigError::Toml(error) => write!(f, "{}", error),
+            ConfigError::Json(error) => write!(f, "{}", error),
+            ConfigError::UnknownFormat(path) => write!(f, "{} should be a .toml or .json file", path),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExperimentConfig {
+    /// Directory the metrics of every solver are appended to, one CSV file per solver.
+    pub output_dir: String,
+    #[serde(default = "default_instances_dir")]
+    pub instances_dir: String,
+    /// Time limit of every run in nanoseconds, unless an instance sets its own.
+    pub time_limit: u64,
+    /// Minimum number of runs of every solver on every instance, for every seed.
+    #[serde(default = "default_runs")]
+    pub runs: usize,
+    #[serde(default = "default_seeds")]
+    pub seeds: Vec<u64>,
+    /// Worker threads the runs are spread over; zero uses one per core.
+    #[serde(default)]
+    pub workers: usize,
+    /// Whether solutions better than the `.sln` file of a QAPLIB instance are written to
+    /// `<output_dir>/best_known/<instance>.sln`.
+    #[serde(default = "default_save_best_known")]
+    pub save_best_known: bool,
+    /// Convergence traces of the runs, written to
+    /// `<output_dir>/traces/<label>/<instance>-<seed>-<run>` in `trace_format`.
+    #[serde(default)]
+    pub tracing: Tracing,
+    #[serde(default)]
+    pub trace_format: TraceFormat,
+    /// Number type the costs of every instance are read, generated and solved in.
+    #[serde(default)]
+    pub cost_type: CostType,
+    pub instances: Vec<InstanceConfig>,
+    pub solvers: Vec<SolverEntry>,
+}
+
+fn default_instances_dir() -> String {
+    "qap/instances".to_string()
+}
+
+fn default_runs() -> usize {
+    10
+}
+
+fn default_seeds() -> Vec<u64> {
+    vec![42]
+}
+
+fn default_save_best_known() -> bool {
+    true
+}
+
+impl ExperimentConfig {
+    /// Reads a `.toml` or `.json` file.
+    pub fn from_file(path: &str) -> Result<ExperimentConfig, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => ExperimentConfig::from_toml(&text),
+            Some("json") => serde_json::from_str(&text).map_err(ConfigError::Json),
+            _ => Err(ConfigError::UnknownFormat(path.to_string())),
+        }
+    }
+
+    pub fn from_toml(text: &str) -> Result<ExperimentConfig, ConfigError> {
+        toml::from_str(text).map_err(ConfigError::Toml)
+    }
+
+    /// Time limit of the runs on `instance`.
+    pub fn time_limit(&self, instance: &InstanceConfig) -> u128 {
+        instance.time_limit.unwrap_or(self.time_limit) as u128
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl TraceFormat {
+    /// Writes the trace of a run to `<path>.csv` or `<path>.json`.
+    pub fn save<C: Cost>(self, path: &str, metrics: &Metrics<C>) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            TraceFormat::Csv => save_trace_to_csv(&format!("{}.csv", path), metrics),
+            TraceFormat::Json => save_trace_to_json(&format!("{}.json", path), metrics),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CostType {
+    #[default]
+    Usize,
+    I64,
+    U64,
+    F64,
+}
+
+/// A QAPLIB instance read from `<name>.dat` and `<name>.sln`, or a generated one.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstanceConfig {
+    pub name: String,
+    /// Whether the `.dat` file holds Lawler costs.
+    #[serde(default)]
+    pub lawler: bool,
+    pub time_limit: Option<u64>,
+    pub generator: Option<GeneratorConfig>,
+}
+
+impl InstanceConfig {
+    pub fn load<C: Cost>(&self, reader: &InstanceReader) -> Result<Instance<C>, ParseError> {
+        match &self.generator {
+            Some(generator) => Ok(generator.generate()),
+            None if self.lawler => reader.read_lawler_instance(&self.name),
+            None => reader.read_instance(&self.name),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum GeneratorConfig {
+    LiPardalos { size: usize, variant: LipaKind, seed: u64 },
+    TaillardA { size: usize, seed: u64 },
+    TaillardB { size: usize, seed: u64 },
+    Nugent { rows: usize, columns: usize, seed: u64 },
+}
+
+impl GeneratorConfig {
+    pub fn generate<C: Cost>(&self) -> Instance<C> {
+        let instance = match *self {
+            GeneratorConfig::LiPardalos { size, variant, seed } => generator::li_pardalos(size, variant, seed),
+            GeneratorConfig::TaillardA { size, seed } => generator::taillard_a(size, seed),
+            GeneratorConfig::TaillardB { size, seed } => generator::taillard_b(size, seed),
+            GeneratorConfig::Nugent { rows, columns, seed } => generator::nugent(rows, columns, seed),
+        };
+        let convert = |matrix: &Matrix| -> Vec<Vec<C>> {
+            matrix.rows().map(|row| row.iter().map(|&cost| C::from_usize(cost)).collect()).collect()
+        };
+        Instance::new(
+            convert(&instance.matrix_a),
+            convert(&instance.matrix_b),
+            C::from_usize(instance.optimal_cost),
+            instance.optimal_permutation,
+        )
+    }
+}
+
+/// A solver and the name of its output file, the solver's own name unless `label` is set.
+#[derive(Deserialize)]
+pub struct SolverEntry {
+    pub label: Option<String>,
+    #[serde(flatten)]
+    pub solver: SolverConfig,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NeighbourhoodConfig {
+    #[default]
+    Swap,
+    ThreeCycle,
+    Insertion,
+    Relocation,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ScheduleConfig {
+    Geometric { alpha: f64 },
+    Linear { epochs: usize },
+    LundyMees { beta: f64 },
+    Logarithmic {},
+    Reheating { alpha: f64, patience: usize, reheat_ratio: f64 },
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        ScheduleConfig::Geometric { alpha: 0.95 }
+    }
+}
+
+impl ScheduleConfig {
+    fn build(&self) -> Box<dyn CoolingSchedule> {
+        match *self {
+            ScheduleConfig::Geometric { alpha } => Box::new(Geometric { alpha }),
+            ScheduleConfig::Linear { epochs } => Box::new(Linear { epochs }),
+            ScheduleConfig::LundyMees { beta } => Box::new(LundyMees { beta }),
+            ScheduleConfig::Logarithmic {} => Box::new(Logarithmic),
+            ScheduleConfig::Reheating { alpha, patience, reheat_ratio } => {
+                Box::new(Reheating::new(alpha, patience, reheat_ratio))
+            }
+        }
+    }
+}
+
+/// A solver with its parameters; parameters left out take the solver's defaults and misspelt
+/// ones are rejected.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SolverConfig {
+    RandomSearch {
+        #[serde(default = "unlimited")]
+        max_iterations: usize,
+    },
+    RandomWalk {
+        #[serde(default = "unlimited")]
+        max_iterations: usize,
+        #[serde(default)]
+        neighbourhood: NeighbourhoodConfig,
+    },
+    Heuristic {},
+    LinearAssignment {},
+    Greedy {
+        #[serde(default)]
+        neighbourhood: NeighbourhoodConfig,
+    },
+    Steepest {
+        #[serde(default)]
+        neighbourhood: NeighbourhoodConfig,
+    },
+    TabuSearch {
+        #[serde(default = "unlimited")]
+        max_evaluations: usize,
+    },
+    SimulatedAnnealing {
+        #[serde(default)]
+        neighbourhood: NeighbourhoodConfig,
+        #[serde(default)]
+        schedule: ScheduleConfig,
+        #[serde(flatten)]
+        config: AnnealingConfig,
+    },
+    IteratedLocalSearch {
+        local_search: LocalSearch,
+        perturbation: Perturbation,
+        acceptance: Acceptance,
+    },
+    VariableNeighbourhood {
+        max_shaking: usize,
+    },
+    Genetic {
+        #[serde(flatten)]
+        config: GeneticConfig,
+    },
+    Grasp {
+        #[serde(flatten)]
+        config: GraspConfig,
+    },
+    AntColony {
+        #[serde(flatten)]
+        config: AntSystemConfig,
+    },
+    BranchAndBound {},
+}
+
+fn unlimited() -> usize {
+    usize::MAX
+}
+
+impl SolverEntry {
+    /// Builds the solver for `instance`.
+    pub fn build<'i, C: Cost>(&self, instance: &'i Instance<C>, max_time: u128) -> Box<dyn Solver<C> + 'i> {
+        match &self.solver {
+            SolverConfig::RandomSearch { max_iterations } => {
+                Box::new(RandomSearchSolver::new(instance, *max_iterations, max_time))
+            }
+            SolverConfig::RandomWalk { max_iterations, neighbourhood } => {
+                with_neighbourhood(instance, *neighbourhood, NeighbourhoodSolver::RandomWalk(*max_iterations), max_time)
+            }
+            SolverConfig::Heuristic {} => Box::new(HeuristicSolver::new(instance)),
+            SolverConfig::LinearAssignment {} => Box::new(LinearAssignmentSolver::new(instance)),
+            SolverConfig::Greedy { neighbourhood } => {
+                with_neighbourhood(instance, *neighbourhood, NeighbourhoodSolver::Greedy, max_time)
+            }
+            SolverConfig::Steepest { neighbourhood } => {
+                with_neighbourhood(instance, *neighbourhood, NeighbourhoodSolver::Steepest, max_time)
+            }
+            SolverConfig::TabuSearch { max_evaluations } => {
+                Box::new(TabuSearchSolver::new(instance, *max_evaluations, max_time))
+            }
+            SolverConfig::SimulatedAnnealing { neighbourhood, schedule, config } => {
+                let annealing = NeighbourhoodSolver::SimulatedAnnealing(config.clone(), schedule.build());
+                with_neighbourhood(instance, *neighbourhood, annealing, max_time)
+            }
+            SolverConfig::IteratedLocalSearch { local_search, perturbation, acceptance } => Box::new(
+                IteratedLocalSearchSolver::new(instance, max_time, *local_search, *perturbation, *acceptance),
+            ),
+            SolverConfig::VariableNeighbourhood { max_shaking } => {
+                Box::new(VariableNeighbourhoodSolver::new(instance, max_time, *max_shaking))
+            }
+            SolverConfig::Genetic { config } => Box::new(GeneticSolver::new(instance, max_time, config.clone())),
+            SolverConfig::Grasp { config } => Box::new(GraspSolver::new(instance, max_time, config.clone())),
+            SolverConfig::AntColony { config } => {
+                Box::new(MaxMinAntSystemSolver::new(instance, max_time, config.clone()))
+            }
+            SolverConfig::BranchAndBound {} => Box::new(BranchAndBoundSolver::new(instance, max_time)),
+        }
+    }
+
+    /// Name of the output file of the solver.
+    pub fn label<C: Cost>(&self, solver: &dyn Solver<C>) -> String {
+        self.label.clone().unwrap_or_else(|| solver.get_name())
+    }
+}
+
+/// Solvers generic over their neighbourhood, with their other parameters.
+enum NeighbourhoodSolver {
+    RandomWalk(usize),
+    Greedy,
+    Steepest,
+    SimulatedAnnealing(AnnealingConfig, Box<dyn CoolingSchedule>),
+}
+
+fn with_neighbourhood<'i, C: Cost>(
+    instance: &'i Instance<C>,
+    neighbourhood: NeighbourhoodConfig,
+    solver: NeighbourhoodSolver,
+    max_time: u128,
+) -> Box<dyn Solver<C> + 'i> {
+    match neighbourhood {
+        NeighbourhoodConfig::Swap => build_with(instance, Swap, solver, max_time),
+        NeighbourhoodConfig::ThreeCycle => build_with(instance, ThreeCycle, solver, max_time),
+        NeighbourhoodConfig::Insertion => build_with(instance, Insertion, solver, max_time),
+        NeighbourhoodConfig::Relocation => build_with(instance, Relocation::new(instance), solver, max_time),
+    }
+}
+
+fn build_with<'i, C: Cost, N: Neighbourhood + 'static>(
+    instance: &'i Instance<C>,
+    neighbourhood: N,
+    solver: NeighbourhoodSolver,
+    max_time: u128,
+) -> Box<dyn Solver<C> + 'i> {
+    match solver {
+        NeighbourhoodSolver::RandomWalk(max_iterations) => {
+            Box::new(RandomWalkSolver::with_neighbourhood(instance, max_iterations, max_time, neighbourhood))
+        }
+        NeighbourhoodSolver::Greedy => Box::new(GreedySolver::with_neighbourhood(instance, max_time, neighbourhood)),
+        NeighbourhoodSolver::Steepest => Box::new(SteepestSolver::with_neighbourhood(instance, max_time, neighbourhood)),
+        NeighbourhoodSolver::SimulatedAnnealing(config, schedule) => Box::new(
+            SimulatedAnnealingSolver::with_neighbourhood(instance, max_time, config, schedule, neighbourhood),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::experiments::run_experiment;
+
+    #[test]
+    fn shipped_configurations_parse() {
+        for path in ["experiments/chr.toml", "experiments/lipa.json", "experiments/suite.toml"] {
+            let config = ExperimentConfig::from_file(path).unwrap();
+            assert!(!config.instances.is_empty() && !config.solvers.is_empty(), "{}", path);
+        }
+        assert!(matches!(ExperimentConfig::from_file("Cargo.lock"), Err(ConfigError::UnknownFormat(_))));
+        assert!(matches!(ExperimentConfig::from_toml("time_limit = 1"), Err(ConfigError::Toml(_))));
+    }
+
+    #[test]
+    fn unknown_solver_parameters_are_rejected() {
+        let parse = |solver: &str| {
+            ExperimentConfig::from_toml(&format!(
+                "output_dir = \"output\"\ntime_limit = 1\ninstances = []\n[[solvers]]\n{}",
+                solver
+            ))
+        };
+        assert!(parse("type = \"tabu_search\"\nlabel = \"TS\"\nmax_evaluations = 10").is_ok());
+        for solver in [
+            "type = \"tabu_search\"\ntenure_min = 10",
+            "type = \"heuristic\"\nmax_iterations = 10",
+            "type = \"simulated_annealing\"\nschedule = { kind = \"logarithmic\", alpha = 0.9 }",
+            "type = \"simulated_annealing\"\nepoch_lenght = 10",
+            "type = \"genetic\"\nselection = { kind = \"tournament\", sise = 2 }",
+            "type = \"grasp\"\nalfa = 0.5",
+            "type = \"ant_colony\"\nant_count = 5",
+            "type = \"iterated_local_search\"\nlocal_search = \"greedy\"\nacceptance = { kind = \"better\" }\n\
+             perturbation = { kind = \"random_swaps\", k = 2, n = 3 }",
+        ] {
+            assert!(matches!(parse(solver), Err(ConfigError::Toml(_))), "{}", solver);
+        }
+    }
+
+    #[test]
+    fn runs_generated_instances() {
+        let output_dir = std::env::temp_dir().join("qap-runs-generated-instances");
+        let _ = std::fs::remove_dir_all(&output_dir);
+        let config = ExperimentConfig::from_toml(&format!(
+            r#"
+            output_dir = "{}"
+            time_limit = 1_000_000
+            runs = 2
+            seeds = [1, 2]
+            tracing = "improvements"
+            trace_format = "json"
+
+            [[instances]]
+            name = "lipa8"
+            generator = {{ kind = "li_pardalos", size = 8, variant = "a", seed = 3 }}
+
+            [[solvers]]
+            type = "simulated_annealing"
+            label = "SA-3"
+            neighbourhood = "three_cycle"
+            schedule = {{ kind = "lundy_mees", beta = 0.01 }}
+            epoch_length = 50
+
+            [[solvers]]
+            type = "iterated_local_search"
+            local_search = "steepest"
+            perturbation = {{ kind = "random_swaps", k = 2 }}
+            acceptance = {{ kind = "restart_after", iterations = 5 }}
+
+            [[solvers]]
+            type = "genetic"
+            population_size = 10
+            selection = {{ kind = "tournament", size = 2 }}
+            "#,
+            output_dir.display()
+        ))
+        .unwrap();
+
+        let instance: Instance = config.instances[0].load(&InstanceReader::new(&config.instances_dir)).unwrap();
+        let names: Vec<String> = config.solvers.iter().map(|entry| entry.label(&*entry.build(&instance, 1))).collect();
+        assert_eq!(vec!["SA-3", "IteratedLocalSearchSolver", "GeneticSolver"], names);
+
+        run_experiment(&config).unwrap();
+        for name in names {
+            let csv = std::fs::read_to_string(output_dir.join(format!("{}.csv", name))).unwrap();
+            assert!(csv.lines().count() > 4, "{}", name);
+            assert!(csv.lines().skip(1).all(|line| line.starts_with("lipa8,")));
+
+            let trace = std::fs::read_to_string(output_dir.join(format!("traces/{}/lipa8-1-0.json", name))).unwrap();
+            let trace: serde_json::Value = serde_json::from_str(&trace).unwrap();
+            assert!(!trace["trace"].as_array().unwrap().is_empty(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn runs_instances_with_other_cost_types() {
+        for cost_type in ["i64", "u64", "f64"] {
+            let output_dir = std::env::temp_dir().join(format!("qap-runs-{}-instances", cost_type));
+            let _ = std::fs::remove_dir_all(&output_dir);
+            let config = ExperimentConfig::from_toml(&format!(
+                r#"
+                output_dir = "{}"
+                time_limit = 1_000_000
+                runs = 1
+                cost_type = "{}"
+
+                [[instances]]
+                name = "lipa6"
+                generator = {{ kind = "li_pardalos", size = 6, variant = "b", seed = 3 }}
+
+                [[solvers]]
+                type = "branch_and_bound"
+
+                [[solvers]]
+                type = "linear_assignment"
+
+                [[solvers]]
+                type = "heuristic"
+                "#,
+                output_dir.display(),
+                cost_type
+            ))
+            .unwrap();
+
+            run_experiment(&config).unwrap();
+            for name in ["BranchAndBoundSolver", "LinearAssignmentSolver", "HeuristicSolver"] {
+                let csv = std::fs::read_to_string(output_dir.join(format!("{}.csv", name))).unwrap();
+                assert_eq!(2, csv.lines().count(), "{} {}", cost_type, name);
+            }
+        }
+        assert!(ExperimentConfig::from_toml(
+            "output_dir = \"output\"\ntime_limit = 1\ncost_type = \"i128\"\ninstances = []\nsolvers = []"
+        )
+        .is_err());
+    }
+}
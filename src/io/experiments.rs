@@ -1,62 +1,279 @@
-use std::usize;
 use crate::{
-    get_random_permutation,
-    io::{save_metrics_to_csv, InstanceReader},
-    measure_time,
+    bounds::Bounds,
+    cost::{cmp_costs, Cost},
+    executor::ParallelExecutor,
+    io::{
+        config::{CostType, ExperimentConfig, TraceFormat},
+        save_metrics_to_csv, InstanceReader, InstanceWriter,
+    },
+    measure_run, measure_time, run_seed, Metrics,
     solver::{
-        heuristic_solver, local_search, random_search::RandomSearchSolver,
-        random_walk::RandomWalkSolver, Solver,
+        heuristic_solver, linear_assignment::LinearAssignmentSolver, local_search,
+        random_search::RandomSearchSolver,
+        random_walk::RandomWalkSolver, monitor::Monitor, tabu_search::TabuSearchSolver, trace::Tracing, Solver,
     },
 };
 use crate::instance::Instance;
 
-pub fn initial_quality_experiment(instances: &[&str], out_dir: &str, runs: usize) {
-    let instance_reader = InstanceReader::new("qap/instances");
-    for instance_name in instances {
-        let instance = instance_reader
-            .read_instance(instance_name)
-            .expect("Failed to read instance file");
-
-        let mut solvers: Vec<Box<dyn Solver>> = get_local_search_solvers(&instance, u128::MAX);
-        solvers.iter_mut().for_each(|solver| {
-            println!("{:?}", solver.get_name());
-            let metrics = measure_time(&mut **solver, &instance, instance_name, runs);
-            let _ =
-                save_metrics_to_csv(&format!("output/{}/{}.csv", out_dir, solver.get_name()), &metrics);
-        });
+const INSTANCES_DIR: &str = "qap/instances";
+
+/// Cost of the best solution known for an instance, starting from its `.sln` file or from
+/// the one saved in `dir` by earlier experiments. Better solutions found by experiments are
+/// written to `<dir>/<instance>.sln`, never to the shipped instance files.
+struct BestKnown<'a, C: Cost = usize> {
+    dir: String,
+    instance_name: &'a str,
+    cost: Option<C>,
+}
+
+impl<'a, C: Cost> BestKnown<'a, C> {
+    fn new(dir: String, instance: &Instance<C>, instance_name: &'a str) -> BestKnown<'a, C> {
+        let shipped = (!instance.optimal_permutation.is_empty()).then_some(instance.optimal_cost);
+        let saved = InstanceReader::new(&dir)
+            .read_optimal_solution::<C>(instance_name)
+            .ok()
+            .map(|(_, cost, _)| cost);
+        BestKnown {
+            dir,
+            instance_name,
+            cost: shipped.into_iter().chain(saved).min_by(cmp_costs),
+        }
+    }
+
+    fn update(&mut self, instance: &Instance<C>, metrics: &[Metrics<C>]) {
+        let Some(best) = metrics.iter().min_by(|first, second| cmp_costs(&first.cost, &second.cost)) else { return };
+        if self.cost.is_some_and(|cost| cost <= best.cost) {
+            return;
+        }
+        let writer = InstanceWriter::new(&self.dir);
+        match writer.write_solution(self.instance_name, best.cost, instance.assignment(&best.permutation)) {
+            Ok(()) => self.cost = Some(best.cost),
+            Err(error) => eprintln!("Failed to save best-known solution of {}: {}", self.instance_name, error),
+        }
+    }
+}
+
+/// An instance of a suite, with the lower bound the gaps of its runs are computed against.
+struct SuiteInstance<'a, C: Cost = usize> {
+    name: &'a str,
+    instance: Instance<C>,
+    lower_bound: C,
+    /// Whether better solutions are saved to `<output_dir>/best_known`.
+    save_best_known: bool,
+}
+
+impl<'a, C: Cost> SuiteInstance<'a, C> {
+    /// Instance bounded by the best bound of the `bounds` module.
+    fn new(name: &'a str, instance: Instance<C>, save_best_known: bool) -> SuiteInstance<'a, C> {
+        SuiteInstance {
+            name,
+            lower_bound: Bounds::compute(&instance).best(),
+            instance,
+            save_best_known,
+        }
+    }
+}
+
+/// Every solver run on every instance, `runs` times for every seed. The runs are independent
+/// and execute in parallel; the metrics of every run are appended to
+/// `<output_dir>/<label>.csv` in the order of the runs.
+struct Suite<'a, C: Cost = usize> {
+    instances: Vec<SuiteInstance<'a, C>>,
+    solvers: usize,
+    seeds: &'a [u64],
+    runs: usize,
+    output_dir: &'a str,
+    /// Format the trace of every run is written in to
+    /// `<output_dir>/traces/<label>/<instance>-<seed>-<run>`, if any.
+    trace_format: Option<TraceFormat>,
+}
+
+/// The `run`-th repetition of a suite seed, with its own seed derived from it.
+struct Run {
+    instance: usize,
+    solver: usize,
+    suite_seed: u64,
+    run: usize,
+    seed: u64,
+    first: bool,
+}
+
+impl<'a, C: Cost> Suite<'a, C> {
+    fn runs(&self) -> Vec<Run> {
+        let mut runs = Vec::new();
+        for instance in 0..self.instances.len() {
+            for solver in 0..self.solvers {
+                for (k, &suite_seed) in self.seeds.iter().enumerate() {
+                    for run in 0..self.runs {
+                        let (seed, first) = (run_seed(suite_seed, run), k == 0 && run == 0);
+                        runs.push(Run { instance, solver, suite_seed, run, seed, first });
+                    }
+                }
+            }
+        }
+        runs
+    }
+
+    /// Runs the suite on `workers` threads; `build(instance, solver, instance)` builds the
+    /// solver of a run and the label of its output file.
+    fn execute<B>(&self, workers: usize, build: B) -> Result<(), String>
+    where
+        B: for<'i> Fn(usize, usize, &'i Instance<C>) -> (String, Box<dyn Solver<C> + 'i>) + Sync,
+    {
+        let runs = self.runs();
+        let mut best_known: Vec<Option<BestKnown<C>>> = self
+            .instances
+            .iter()
+            .map(|entry| {
+                let dir = best_known_dir(self.output_dir);
+                entry.save_best_known.then(|| BestKnown::new(dir, &entry.instance, entry.name))
+            })
+            .collect();
+        let mut error = None;
+
+        ParallelExecutor::new(workers).execute(
+            &runs,
+            |run| {
+                let entry = &self.instances[run.instance];
+                let (label, mut solver) = build(run.instance, run.solver, &entry.instance);
+                (label, measure_run(&mut *solver, &entry.instance, entry.name, run.seed, entry.lower_bound))
+            },
+            |run, (label, metrics)| {
+                if run.first {
+                    println!("{} {}", metrics.instance_name, label);
+                }
+                let metrics = std::slice::from_ref(&metrics);
+                if let Some(best_known) = best_known[run.instance].as_mut() {
+                    best_known.update(&self.instances[run.instance].instance, metrics);
+                }
+                if let Err(failure) = save_metrics_to_csv(&format!("{}/{}.csv", self.output_dir, label), metrics) {
+                    error.get_or_insert_with(|| failure.to_string());
+                }
+                if let Some(format) = self.trace_format {
+                    let name = &metrics[0].instance_name;
+                    let path = format!("{}/traces/{}/{}-{}-{}", self.output_dir, label, name, run.suite_seed, run.run);
+                    if let Err(failure) = format.save(&path, &metrics[0]) {
+                        error.get_or_insert_with(|| failure.to_string());
+                    }
+                }
+            },
+        );
+        error.map_or(Ok(()), Err)
+    }
+}
+
+/// Runs every solver of `config` on every instance, appending the metrics to
+/// `<output_dir>/<label>.csv`.
+pub fn run_experiment(config: &ExperimentConfig) -> Result<(), Box<dyn std::error::Error>> {
+    match config.cost_type {
+        CostType::Usize => run_experiment_with::<usize>(config),
+        CostType::I64 => run_experiment_with::<i64>(config),
+        CostType::U64 => run_experiment_with::<u64>(config),
+        CostType::F64 => run_experiment_with::<f64>(config),
     }
 }
 
-pub fn run_all_algorithms(instances: &[&str], out_dir: &str, limits: &[u128]) {
-    let instance_reader = InstanceReader::new("qap/instances");
-    for (i, instance_name) in instances.iter().enumerate() {
-        let instance = instance_reader
-            .read_instance(instance_name)
-            .expect("Failed to read instance file");
-
-        let mut solvers: Vec<Box<dyn Solver>> = get_all_solvers(&instance, u128::MAX);
-        solvers.iter_mut().for_each(|solver| {
-            println!("{:?}", solver.get_name());
-            solver.set_time_limit(limits[i]);
-            let metrics = measure_time(&mut **solver, &instance, instance_name, 10);
-            let _ =
-                save_metrics_to_csv(&format!("output/{}/{}.csv", out_dir, solver.get_name()), &metrics);
-        });
+fn run_experiment_with<C: Cost>(config: &ExperimentConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let instance_reader = InstanceReader::new(&config.instances_dir);
+    let mut instances = Vec::new();
+    for instance_config in &config.instances {
+        let save_best_known = config.save_best_known && instance_config.generator.is_none();
+        let instance = instance_config.load::<C>(&instance_reader)?;
+        instances.push(SuiteInstance::new(&instance_config.name, instance, save_best_known));
+    }
+    let suite = Suite {
+        instances,
+        solvers: config.solvers.len(),
+        seeds: &config.seeds,
+        runs: config.runs,
+        output_dir: &config.output_dir,
+        trace_format: (config.tracing != Tracing::Off).then_some(config.trace_format),
     };
+
+    suite.execute(config.workers, |i, s, instance| {
+        let entry = &config.solvers[s];
+        let mut solver = entry.build(instance, config.time_limit(&config.instances[i]));
+        solver.set_monitor(Monitor::new().with_tracing(config.tracing));
+        (entry.label(&*solver), solver)
+    })?;
+    Ok(())
+}
+
+/// Directory the best-known solutions found by the experiments writing to `output_dir` are
+/// saved to.
+fn best_known_dir(output_dir: &str) -> String {
+    format!("{}/best_known", output_dir)
 }
 
-pub fn run_alg_with_time_constrains(solver_name: &str, instance_name: &str, time_limits: &[u128]) {
-    let instance_reader = InstanceReader::new("qap/instances");
+fn read_instances<'a>(instances: &[&'a str]) -> Vec<SuiteInstance<'a>> {
+    let instance_reader = InstanceReader::new(INSTANCES_DIR);
+    instances
+        .iter()
+        .map(|&name| SuiteInstance::new(name, instance_reader.read_instance(name).expect("Failed to read instance file"), true))
+        .collect()
+}
+
+pub fn initial_quality_experiment(instances: &[&str], out_dir: &str, runs: usize, seed: u64, workers: usize) {
+    let output_dir = format!("output/{}", out_dir);
+    let suite = Suite {
+        instances: read_instances(instances),
+        solvers: LOCAL_SEARCH_SOLVERS.len(),
+        seeds: &[seed],
+        runs,
+        output_dir: &output_dir,
+        trace_format: None,
+    };
+    let result = suite.execute(workers, |_, s, instance| {
+        let solver = LOCAL_SEARCH_SOLVERS[s](instance, u128::MAX);
+        (solver.get_name(), solver)
+    });
+    if let Err(error) = result {
+        eprintln!("Failed to save metrics: {}", error);
+    }
+}
+
+pub fn run_all_algorithms(instances: &[&str], out_dir: &str, limits: &[u128], seed: u64, workers: usize) {
+    let output_dir = format!("output/{}", out_dir);
+    let suite = Suite {
+        instances: read_instances(instances),
+        solvers: ALL_SOLVERS.len(),
+        seeds: &[seed],
+        runs: 10,
+        output_dir: &output_dir,
+        trace_format: None,
+    };
+    let result = suite.execute(workers, |i, s, instance| {
+        let solver = ALL_SOLVERS[s](instance, limits[i]);
+        (solver.get_name(), solver)
+    });
+    if let Err(error) = result {
+        eprintln!("Failed to save metrics: {}", error);
+    }
+}
+
+pub fn run_alg_with_time_constrains(
+    solver_name: &str,
+    instance_name: &str,
+    time_limits: &[u128],
+    seed: u64,
+) {
+    let instance_reader = InstanceReader::new(INSTANCES_DIR);
     let instance = instance_reader
         .read_instance(instance_name)
         .expect("Failed to read instance file");
+    let mut best_known = BestKnown::new(best_known_dir("output/times2"), &instance, instance_name);
+    let lower_bound = Bounds::compute(&instance).best();
 
-    let mut solvers = get_all_solvers(&instance, 2_500_000);
-    let mut solver = solvers.iter_mut().find(|s| s.get_name() == solver_name).expect("Solver not found");
+    let mut solver = ALL_SOLVERS
+        .iter()
+        .map(|build| build(&instance, 2_500_000))
+        .find(|s| s.get_name() == solver_name)
+        .expect("Solver not found");
 
     time_limits.iter().for_each(|limit| {
         solver.set_time_limit(*limit);
-        let metrics = measure_time(&mut **solver, &instance, instance_name, 100);
+        let metrics = measure_time(&mut *solver, &instance, instance_name, 100, seed, lower_bound);
+        best_known.update(&instance, &metrics);
         let _ = save_metrics_to_csv(
             &format!("output/times2/{}.csv", solver.get_name()),
             &metrics,
@@ -64,35 +281,37 @@ pub fn run_alg_with_time_constrains(solver_name: &str, instance_name: &str, time
     });
 }
 
-fn get_all_solvers<'i>(instance: &'i Instance, max_time: u128) -> Vec<Box<dyn Solver + 'i>> {
-    let mut solvers: Vec<Box<dyn Solver>> = vec![
-        Box::new(RandomSearchSolver::new(&instance, usize::MAX, max_time)),
-        Box::new(RandomWalkSolver::new(&instance, usize::MAX, max_time)),
-        Box::new(heuristic_solver::HeuristicSolver::new(&instance)),
-        Box::new(local_search::greedy::GreedySolver::new(
-            &instance,
-            max_time,
-        )),
-        Box::new(local_search::steepest::SteepestSolver::new(
-            &instance,
-            max_time,
-        )),
-    ];
-    solvers
-}
+/// Builds a solver of an instance with a time limit.
+type SolverBuilder = for<'i> fn(&'i Instance, u128) -> Box<dyn Solver + 'i>;
+
+/// The solvers compared by `run_all_algorithms`.
+const ALL_SOLVERS: &[SolverBuilder] = &[
+    |instance, max_time| Box::new(RandomSearchSolver::new(instance, usize::MAX, max_time)),
+    |instance, max_time| Box::new(RandomWalkSolver::new(instance, usize::MAX, max_time)),
+    |instance, _| Box::new(heuristic_solver::HeuristicSolver::new(instance)),
+    |instance, _| Box::new(LinearAssignmentSolver::new(instance)),
+    |instance, max_time| Box::new(local_search::greedy::GreedySolver::new(instance, max_time)),
+    |instance, max_time| Box::new(local_search::steepest::SteepestSolver::new(instance, max_time)),
+    |instance, max_time| Box::new(TabuSearchSolver::new(instance, usize::MAX, max_time)),
+];
+
+/// The solvers compared by `initial_quality_experiment`.
+const LOCAL_SEARCH_SOLVERS: &[SolverBuilder] = &[
+    |instance, max_time| Box::new(local_search::greedy::GreedySolver::new(instance, max_time)),
+    |instance, max_time| Box::new(local_search::steepest::SteepestSolver::new(instance, max_time)),
+];
 
 pub fn get_local_search_solvers<'i>(instance: &'i Instance, max_time: u128) -> Vec<Box<dyn Solver + 'i>> {
-    vec![
-        Box::new(local_search::greedy::GreedySolver::new(&instance, max_time)),
-        Box::new(local_search::steepest::SteepestSolver::new(&instance, max_time)),
-    ]
+    LOCAL_SEARCH_SOLVERS.iter().map(|build| build(instance, max_time)).collect()
 }
 
-pub fn run_all_algorithms_with_time_constrains() {
-    let instance_reader = InstanceReader::new("qap/instances");
-    let instance = instance_reader
+pub fn run_all_algorithms_with_time_constrains(seed: u64) {
+    let instance_reader = InstanceReader::new(INSTANCES_DIR);
+    let instance: Instance = instance_reader
         .read_instance("chr12a")
         .expect("Failed to read instance file");
+    let mut best_known = BestKnown::new(best_known_dir("output/times2"), &instance, "chr12a");
+    let lower_bound = Bounds::compute(&instance).best();
 
     let mut steepest_solver = Box::new(local_search::steepest::SteepestSolver::new(
         &instance, 10_000_000,
@@ -111,7 +330,10 @@ pub fn run_all_algorithms_with_time_constrains() {
             &instance,
             "chr12a",
             100,
+            seed,
+            lower_bound,
         );
+        best_known.update(&instance, &metrics);
 
         let _ = save_metrics_to_csv(
             &format!("output/times2/{}.csv", steepest_solver.get_name()),
@@ -119,3 +341,26 @@ pub fn run_all_algorithms_with_time_constrains() {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{li_pardalos, LipaKind};
+
+    #[test]
+    fn runs_have_distinct_seeds() {
+        let seeds: Vec<u64> = (0..20).collect();
+        let suite = Suite {
+            instances: vec![SuiteInstance::new("lipa5", li_pardalos(5, LipaKind::A, 1), false)],
+            solvers: 1,
+            seeds: &seeds,
+            runs: 20,
+            output_dir: "",
+            trace_format: None,
+        };
+        let mut run_seeds: Vec<u64> = suite.runs().iter().map(|run| run.seed).collect();
+        run_seeds.sort_unstable();
+        run_seeds.dedup();
+        assert_eq!(seeds.len() * suite.runs, run_seeds.len());
+    }
+}
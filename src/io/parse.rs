@@ -0,0 +1,243 @@
+//! Tokenizer for the whitespace separated number files of QAPLIB, reporting errors with the
+//! file, line and column they occur at.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::cost::Cost;
+
+#[derive(Debug)]
+pub enum ParseErrorKind {
+    Io(std::io::Error),
+    UnexpectedEof { expected: &'static str },
+    InvalidNumber { token: String, expected: &'static str },
+    InvalidSize { reason: &'static str },
+    /// Totals of the instance would overflow the cost type it is read as.
+    CostsTooLarge { cost_type: &'static str },
+    TrailingData,
+    /// The solution places a different number of facilities than the instance has.
+    SizeMismatch { expected: usize, found: usize },
+    LocationOutOfRange { location: usize, locations: usize },
+    DuplicateLocation { location: usize },
+}
+
+/// Error at 1-based `line` and `column` of `file`; both are 0 for errors of the whole file.
+#[derive(Debug)]
+pub struct ParseError {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl ParseError {
+    pub fn is_not_found(&self) -> bool {
+        matches!(&self.kind, ParseErrorKind::Io(error) if error.kind() == std::io::ErrorKind::NotFound)
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::Io(error) => write!(f, "{}", error),
+            ParseErrorKind::UnexpectedEof { expected } => write!(f, "Unexpected end of file, expected {}", expected),
+            ParseErrorKind::InvalidNumber { token, expected } => write!(f, "Invalid {} '{}'", expected, token),
+            ParseErrorKind::InvalidSize { reason } => write!(f, "Invalid size: {}", reason),
+            ParseErrorKind::CostsTooLarge { cost_type } => write!(f, "Costs are too large for {}", cost_type),
+            ParseErrorKind::TrailingData => write!(f, "Unexpected data after the end of the file"),
+            ParseErrorKind::SizeMismatch { expected, found } => {
+                write!(f, "Solution of size {} for an instance of {} facilities", found, expected)
+            }
+            ParseErrorKind::LocationOutOfRange { location, locations } => {
+                write!(f, "Location {} is not in 1..={}", location, locations)
+            }
+            ParseErrorKind::DuplicateLocation { location } => write!(f, "Location {} is used twice", location),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file.display(), self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ParseErrorKind::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Contents of a file to parse.
+pub struct Source {
+    pub path: PathBuf,
+    pub text: String,
+}
+
+impl Source {
+    pub fn read(path: PathBuf) -> Result<Source, ParseError> {
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Ok(Source { path, text }),
+            Err(error) => Err(ParseError {
+                file: path,
+                line: 0,
+                column: 0,
+                kind: ParseErrorKind::Io(error),
+            }),
+        }
+    }
+
+    pub fn new(path: impl Into<PathBuf>, text: impl Into<String>) -> Source {
+        Source {
+            path: path.into(),
+            text: text.into(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+    column: usize,
+}
+
+/// Numbers of a file separated by whitespace or commas, consumed from the front.
+pub struct Tokens<'a> {
+    file: &'a Path,
+    tokens: Vec<Token<'a>>,
+    position: usize,
+    /// Line and column just past the end of the file.
+    end: (usize, usize),
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(source: &'a Source) -> Tokens<'a> {
+        let mut tokens = Vec::new();
+        let mut end = (1, 1);
+        for (index, line) in source.text.lines().enumerate() {
+            let mut start = None;
+            for (offset, character) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+                let separator = character.is_whitespace() || character == ',';
+                match (start, separator) {
+                    (None, false) => start = Some(offset),
+                    (Some(begin), true) => {
+                        tokens.push(Token {
+                            text: &line[begin..offset],
+                            line: index + 1,
+                            column: line[..begin].chars().count() + 1,
+                        });
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+            end = (index + 1, line.chars().count() + 1);
+        }
+        Tokens {
+            file: &source.path,
+            tokens,
+            position: 0,
+            end,
+        }
+    }
+
+    fn error_at(&self, token: Option<Token>, kind: ParseErrorKind) -> ParseError {
+        let (line, column) = token.map_or(self.end, |token| (token.line, token.column));
+        ParseError {
+            file: self.file.to_path_buf(),
+            line,
+            column,
+            kind,
+        }
+    }
+
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn previous(&self) -> Option<Token<'a>> {
+        self.position.checked_sub(1).and_then(|position| self.tokens.get(position).copied())
+    }
+
+    /// Error at the token read last.
+    pub fn error(&self, kind: ParseErrorKind) -> ParseError {
+        self.error_at(self.previous(), kind)
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.position == self.tokens.len()
+    }
+
+    pub fn expect_end(&self) -> Result<(), ParseError> {
+        match self.peek() {
+            None => Ok(()),
+            token => Err(self.error_at(token, ParseErrorKind::TrailingData)),
+        }
+    }
+
+    pub fn next_number<T: FromStr>(&mut self, expected: &'static str) -> Result<T, ParseError> {
+        let token = self
+            .peek()
+            .ok_or_else(|| self.error_at(None, ParseErrorKind::UnexpectedEof { expected }))?;
+        let number = token.text.parse().map_err(|_| {
+            let kind = ParseErrorKind::InvalidNumber {
+                token: token.text.to_string(),
+                expected,
+            };
+            self.error_at(Some(token), kind)
+        })?;
+        self.position += 1;
+        Ok(number)
+    }
+
+    /// Finite cost of type `C`.
+    pub fn next_cost<C: Cost>(&mut self) -> Result<C, ParseError> {
+        let token = self.peek();
+        let cost: C = self.next_number("cost")?;
+        match token {
+            Some(token) if !cost.to_f64().is_finite() => {
+                let kind = ParseErrorKind::InvalidNumber {
+                    token: token.text.to_string(),
+                    expected: "cost",
+                };
+                Err(self.error_at(Some(token), kind))
+            }
+            _ => Ok(cost),
+        }
+    }
+
+    /// All numbers on the line of the next one.
+    pub fn line_numbers<T: FromStr>(&mut self, expected: &'static str) -> Result<Vec<T>, ParseError> {
+        let line = self
+            .peek()
+            .ok_or_else(|| self.error_at(None, ParseErrorKind::UnexpectedEof { expected }))?
+            .line;
+        let mut numbers = Vec::new();
+        while self.peek().is_some_and(|token| token.line == line) {
+            numbers.push(self.next_number(expected)?);
+        }
+        Ok(numbers)
+    }
+
+    /// Matrix of `rows x columns` costs, read row by row whatever the line breaks: QAPLIB
+    /// files such as tai*, sko* and wil* wrap long rows over several lines. The size is checked
+    /// against the numbers left in the file before anything is allocated.
+    pub fn matrix<C: Cost>(&mut self, rows: usize, columns: usize) -> Result<Vec<Vec<C>>, ParseError> {
+        let left = self.tokens.len() - self.position;
+        if rows.checked_mul(columns).is_none_or(|entries| entries > left) {
+            let kind = ParseErrorKind::InvalidSize { reason: "matrix has more entries than the file" };
+            return Err(self.error_at(self.peek(), kind));
+        }
+        let mut matrix = Vec::new();
+        for _ in 0..rows {
+            let row = (0..columns).map(|_| self.next_cost()).collect::<Result<_, _>>()?;
+            matrix.push(row);
+        }
+        Ok(matrix)
+    }
+}
@@ -0,0 +1,145 @@
+//! Linear assignment problem: pick one column for every row of a cost matrix so that no
+//! column is used twice and the total cost is minimal.
+
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// Number type the LAP is solved in, such as the wide type of a `Cost`.
+pub trait LapCost: Copy + PartialOrd + Default + Add<Output = Self> + Sub<Output = Self> + AddAssign + SubAssign + Sum {}
+
+impl<T> LapCost for T where
+    T: Copy + PartialOrd + Default + Add<Output = T> + Sub<Output = T> + AddAssign + SubAssign + Sum
+{
+}
+
+/// Optimal assignment found by `solve`.
+#[derive(Debug)]
+pub struct Assignment<T = i64> {
+    pub cost: T,
+    /// Column assigned to every row.
+    pub columns: Vec<usize>,
+    /// Dual solution: `costs[i][j] - row_potentials[i] - col_potentials[j]` is non-negative
+    /// and zero on the assigned cells.
+    pub row_potentials: Vec<T>,
+    pub col_potentials: Vec<T>,
+}
+
+/// Solves the LAP with the shortest augmenting path form of the Hungarian algorithm used by
+/// Jonker and Volgenant: rows are added one at a time and matched along a Dijkstra-like
+/// search over reduced costs. Runs in O(n^2 m) for `n` rows and `m >= n` columns.
+///
+/// # Panics
+///
+/// Panics if the matrix has more rows than columns.
+pub fn solve<T: LapCost>(costs: &[Vec<T>]) -> Assignment<T> {
+    let n = costs.len();
+    let m = costs.first().map_or(0, |row| row.len());
+    assert!(n <= m, "LAP needs at least as many columns as rows");
+
+    let mut row_potential = vec![T::default(); n + 1];
+    let mut col_potential = vec![T::default(); m + 1];
+    // Row matched to every column, 1-based; column 0 is the virtual root of the search.
+    let mut matched_row = vec![0; m + 1];
+    let mut previous_col = vec![0; m + 1];
+
+    for row in 1..=n {
+        matched_row[0] = row;
+        let mut col = 0;
+        // Smallest reduced cost of reaching every column so far; `None` until it is reached.
+        let mut min_slack: Vec<Option<T>> = vec![None; m + 1];
+        let mut visited = vec![false; m + 1];
+        loop {
+            visited[col] = true;
+            let current_row = matched_row[col];
+            let mut delta = None;
+            let mut next_col = 0;
+            for j in 1..=m {
+                if !visited[j] {
+                    let slack = costs[current_row - 1][j - 1] - row_potential[current_row] - col_potential[j];
+                    let min = match min_slack[j] {
+                        Some(min) if min <= slack => min,
+                        _ => {
+                            min_slack[j] = Some(slack);
+                            previous_col[j] = col;
+                            slack
+                        }
+                    };
+                    if delta.is_none_or(|delta| min < delta) {
+                        delta = Some(min);
+                        next_col = j;
+                    }
+                }
+            }
+            let delta = delta.expect("An unmatched row always has an unvisited column");
+            for j in 0..=m {
+                if visited[j] {
+                    row_potential[matched_row[j]] += delta;
+                    col_potential[j] -= delta;
+                } else if let Some(slack) = min_slack[j].as_mut() {
+                    *slack -= delta;
+                }
+            }
+            col = next_col;
+            if matched_row[col] == 0 {
+                break;
+            }
+        }
+        while col != 0 {
+            let previous = previous_col[col];
+            matched_row[col] = matched_row[previous];
+            col = previous;
+        }
+    }
+
+    let mut columns = vec![0; n];
+    for col in 1..=m {
+        if matched_row[col] != 0 {
+            columns[matched_row[col] - 1] = col - 1;
+        }
+    }
+    let cost = columns.iter().enumerate().map(|(row, &col)| costs[row][col]).sum();
+
+    Assignment {
+        cost,
+        columns,
+        row_potentials: row_potential[1..].to_vec(),
+        col_potentials: col_potential[1..].to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solves_square_assignment() {
+        let costs = vec![vec![4, 1, 3], vec![2, 0, 5], vec![3, 2, 2]];
+        let assignment = solve(&costs);
+
+        assert_eq!(5, assignment.cost);
+        assert_eq!(vec![1, 0, 2], assignment.columns);
+    }
+
+    #[test]
+    fn solves_rectangular_assignment_with_valid_duals() {
+        let costs = vec![vec![7, 3, 9, 1], vec![2, 8, 4, 1]];
+        let assignment = solve(&costs);
+
+        assert_eq!(3, assignment.cost);
+        assert_eq!(vec![3, 0], assignment.columns);
+        for (i, row) in costs.iter().enumerate() {
+            for (j, &cost) in row.iter().enumerate() {
+                assert!(cost - assignment.row_potentials[i] - assignment.col_potentials[j] >= 0);
+            }
+        }
+    }
+
+    #[test]
+    fn solves_real_assignment_with_negative_costs() {
+        let costs = vec![vec![-1.5, 2.0], vec![0.25, -3.0]];
+        let assignment = solve(&costs);
+
+        assert_eq!(-4.5, assignment.cost);
+        assert_eq!(vec![0, 1], assignment.columns);
+    }
+}
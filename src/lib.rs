@@ -1,118 +1,420 @@
+pub mod bounds;
+pub mod constraints;
+pub mod cost;
+pub mod executor;
+pub mod generator;
 pub mod io;
+pub mod lap;
+pub mod matrix;
 
+use cost::Cost;
 use instance::Instance;
-use solver::Solver;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use solver::{trace::TracePoint, Solver};
 
-pub struct Metrics {
+/// Outcome of one run of a solver on an instance of cost type `C`.
+pub struct Metrics<C: Cost = usize> {
     pub duration: u128,
     pub instance_name: String,
-    pub cost: usize,
+    pub cost: C,
     pub evaluated_solutions: usize,
     pub solution_changes: usize,
-    pub optimal_cost: usize,
-    pub initial_cost: usize,
+    pub optimal_cost: C,
+    pub initial_cost: C,
     pub time_limit: u128,
     pub solution_distance: usize,
+    pub seed: u64,
+    /// Lower bound on the optimum, e.g. the best one from the `bounds` module.
+    pub lower_bound: C,
+    /// `(cost - lower_bound) / lower_bound`; an upper limit on the relative distance from
+    /// the optimum.
+    pub gap: f64,
+    /// Solution found by the run.
+    pub permutation: Vec<usize>,
+    /// Convergence trace of the run, see `Solver::set_monitor`.
+    pub trace: Vec<TracePoint>,
 }
 
-pub fn measure_time(
-    solver: &mut dyn Solver,
-    instance: &Instance,
+/// Relative gap between a cost and a lower bound on the optimum.
+pub fn optimality_gap<C: Cost>(cost: C, lower_bound: C) -> f64 {
+    let (cost, lower_bound) = (cost.to_f64(), lower_bound.to_f64());
+    if cost <= lower_bound {
+        0.0
+    } else if lower_bound <= 0.0 {
+        f64::INFINITY
+    } else {
+        (cost - lower_bound) / lower_bound
+    }
+}
+
+/// Derives the seed of the `run`-th repetition from the experiment seed, so that every run
+/// can be replayed on its own with `StdRng::seed_from_u64(run_seed(seed, run))`. Seeds are
+/// mixed with SplitMix64, so the runs of adjacent experiment seeds do not share seeds.
+pub fn run_seed(seed: u64, run: usize) -> u64 {
+    splitmix64(splitmix64(seed).wrapping_add(run as u64))
+}
+
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Repeats `measure_run` with the seeds derived from `seed` for at least `min_runs` runs.
+pub fn measure_time<C: Cost>(
+    solver: &mut dyn Solver<C>,
+    instance: &Instance<C>,
     instance_name: &str,
     min_runs: usize,
-) -> Vec<Metrics> {
+    seed: u64,
+    lower_bound: C,
+) -> Vec<Metrics<C>> {
     let mut iteration: usize = 0;
     let mut total_elapsed = 0;
-    let mut total_cost = 0;
-    let mut metrics: Vec<Metrics> = Vec::new();
-    while total_elapsed < 1 || iteration < min_runs as usize {
-        let starting_perm = get_random_permutation(instance.get_size());
-        let initial_cost = instance.evaluate(starting_perm.as_ref());
-        let start = std::time::Instant::now();
-        let solution = solver.solve(starting_perm).expect("Failed to solve");
-        let elapsed = start.elapsed().as_nanos();
-        total_elapsed += elapsed;
-        let cost = solver.get_instance().evaluate(&solution.permutation);
-        total_cost += cost;
+    let mut metrics: Vec<Metrics<C>> = Vec::new();
+    while total_elapsed < 1 || iteration < min_runs {
+        let run = measure_run(solver, instance, instance_name, run_seed(seed, iteration), lower_bound);
+        total_elapsed += run.duration;
         iteration += 1;
-
-        metrics.push(Metrics {
-            duration: elapsed,
-            instance_name: instance_name.to_string(),
-            cost,
-            evaluated_solutions: solution.evaluations,
-            solution_changes: solution.solution_changes,
-            optimal_cost: solver.get_instance().optimal_cost,
-            initial_cost,
-            time_limit: solver.get_time_limit(),
-            solution_distance: instance.get_solutions_distance(&solution.permutation),
-        });
+        metrics.push(run);
     }
     metrics
 }
 
+/// Solves `instance` once from a random permutation, drawing every random decision from
+/// `seed`. `lower_bound` is a lower bound on the optimum the gap is computed against.
+pub fn measure_run<C: Cost>(
+    solver: &mut dyn Solver<C>,
+    instance: &Instance<C>,
+    instance_name: &str,
+    seed: u64,
+    lower_bound: C,
+) -> Metrics<C> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let starting_perm = instance.random_permutation(&mut rng);
+    let initial_cost = instance.evaluate(starting_perm.as_ref()).expect("Starting permutation should be feasible");
+    let start = std::time::Instant::now();
+    let solution = solver.solve(starting_perm, &mut rng).expect("Failed to solve");
+    let elapsed = start.elapsed().as_nanos();
+    let cost = solver.get_instance().evaluate(&solution.permutation).expect("Solution should be feasible");
+
+    Metrics {
+        duration: elapsed,
+        instance_name: instance_name.to_string(),
+        cost,
+        evaluated_solutions: solution.evaluations,
+        solution_changes: solution.solution_changes,
+        optimal_cost: solver.get_instance().optimal_cost,
+        initial_cost,
+        time_limit: solver.get_time_limit(),
+        solution_distance: instance.get_solutions_distance(&solution.permutation),
+        seed,
+        lower_bound,
+        gap: optimality_gap(cost, lower_bound),
+        permutation: solution.permutation,
+        trace: solution.trace,
+    }
+}
+
 pub mod instance {
+    use std::fmt;
+
+    use rand::Rng;
+
     use crate::argsort;
+    use crate::constraints::{ConstraintError, Constraints};
+    use crate::cost::{fits_wide, magnitude, Cost, CostOverflow};
+    use crate::matrix::{LawlerCosts, Matrix};
     use crate::solver::dot_product_permuted;
 
+    /// Why a solution could not be evaluated.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum EvaluationError {
+        Constraint(ConstraintError),
+        Overflow(CostOverflow),
+    }
+
+    impl fmt::Display for EvaluationError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                EvaluationError::Constraint(error) => write!(f, "{}", error),
+                EvaluationError::Overflow(error) => write!(f, "{}", error),
+            }
+        }
+    }
+
+    impl std::error::Error for EvaluationError {}
+
+    impl From<ConstraintError> for EvaluationError {
+        fn from(error: ConstraintError) -> EvaluationError {
+            EvaluationError::Constraint(error)
+        }
+    }
+
+    impl From<CostOverflow> for EvaluationError {
+        fn from(error: CostOverflow) -> EvaluationError {
+            EvaluationError::Overflow(error)
+        }
+    }
+
+    /// Objective function of an instance.
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Formulation<C: Cost = usize> {
+        /// Koopmans–Beckmann form `sum_ij a[i][j] * b[p(i)][p(j)]`.
+        KoopmansBeckmann,
+        /// Koopmans–Beckmann form plus the installation cost `sum_i c[i][p(i)]` of placing
+        /// facility `i` at location `p(i)`.
+        Linear(Matrix<C>),
+        /// Lawler form `sum_ij d[i][j][p(i)][p(j)]`; both matrices of the instance are zero.
+        Lawler(LawlerCosts<C>),
+    }
+
+    /// QAP instance with flows `matrix_a` and distances `matrix_b` of cost type `C`.
+    ///
+    /// An instance may have fewer facilities than locations. The flows are then padded with
+    /// dummy facilities that have no flow, so solutions remain permutations of all `size`
+    /// locations, and the first `facilities` entries place the real facilities.
     #[derive(Debug)]
-    pub struct Instance {
-        pub matrix_a: Vec<Vec<usize>>,
-        pub matrix_b: Vec<Vec<usize>>,
+    pub struct Instance<C: Cost = usize> {
+        pub matrix_a: Matrix<C>,
+        pub matrix_b: Matrix<C>,
+        pub formulation: Formulation<C>,
         pub size: usize,
-        pub optimal_cost: usize,
+        pub facilities: usize,
+        pub optimal_cost: C,
         pub optimal_permutation: Vec<usize>,
+        constraints: Constraints,
     }
 
-    impl Instance {
+    impl<C: Cost> Instance<C> {
+        /// Instance with `n x n` flows and `m x m` distances for `n <= m`. The optimal
+        /// permutation may list only the locations of the `n` facilities.
         pub fn new(
-            matrix_a: Vec<Vec<usize>>,
-            matrix_b: Vec<Vec<usize>>,
-            optimal_cost: usize,
+            matrix_a: Vec<Vec<C>>,
+            matrix_b: Vec<Vec<C>>,
+            optimal_cost: C,
+            optimal_permutation: Vec<usize>,
+        ) -> Instance<C> {
+            let (facilities, size) = (matrix_a.len(), matrix_b.len());
+            assert!(facilities <= size, "Instance should not have more facilities than locations");
+            assert!(matrix_a.iter().all(|row| row.len() == facilities), "Matrix should be square");
+            let matrix_a = Matrix::new(pad(matrix_a, size));
+            let mut instance = Instance::from_matrices(matrix_a, Matrix::new(matrix_b), optimal_cost, optimal_permutation);
+            instance.facilities = facilities;
+            if !instance.optimal_permutation.is_empty() {
+                instance.optimal_permutation = instance.complete_assignment(&instance.optimal_permutation);
+            }
+            instance
+        }
+
+        pub fn from_matrices(
+            matrix_a: Matrix<C>,
+            matrix_b: Matrix<C>,
+            optimal_cost: C,
             optimal_permutation: Vec<usize>,
-        ) -> Instance {
-            let size = matrix_a.len();
-            Instance {
+        ) -> Instance<C> {
+            let size = matrix_a.get_size();
+            let instance = Instance {
                 matrix_a,
                 matrix_b,
+                formulation: Formulation::KoopmansBeckmann,
                 size,
+                facilities: size,
                 optimal_cost,
                 optimal_permutation,
-            }
+                constraints: Constraints::new(size),
+            };
+            instance.assert_fits_wide();
+            instance
+        }
+
+        /// Koopmans–Beckmann instance with the installation costs `linear_costs[i][k]` of
+        /// facility `i` at location `k`, one row per facility and one column per location.
+        pub fn with_linear_costs(
+            matrix_a: Vec<Vec<C>>,
+            matrix_b: Vec<Vec<C>>,
+            linear_costs: Vec<Vec<C>>,
+            optimal_cost: C,
+            optimal_permutation: Vec<usize>,
+        ) -> Instance<C> {
+            assert!(
+                linear_costs.len() == matrix_a.len() && linear_costs.iter().all(|row| row.len() == matrix_b.len()),
+                "Linear costs should match the matrices"
+            );
+            let linear_costs = Matrix::new(pad(linear_costs, matrix_b.len()));
+            let instance = Instance {
+                formulation: Formulation::Linear(linear_costs),
+                ..Instance::new(matrix_a, matrix_b, optimal_cost, optimal_permutation)
+            };
+            instance.assert_fits_wide();
+            instance
+        }
+
+        /// Lawler instance with the four-index costs `costs`.
+        pub fn from_lawler_costs(costs: LawlerCosts<C>, optimal_cost: C, optimal_permutation: Vec<usize>) -> Instance<C> {
+            let size = costs.get_size();
+            let zero = || Matrix::from_flat(size, vec![C::default(); size * size]);
+            let instance = Instance {
+                formulation: Formulation::Lawler(costs),
+                ..Instance::from_matrices(zero(), zero(), optimal_cost, optimal_permutation)
+            };
+            instance.assert_fits_wide();
+            instance
+        }
+
+        /// Panics when the costs are too large for every total and delta to fit into `C::Wide`.
+        fn assert_fits_wide(&self) {
+            let quadratic = magnitude(self.matrix_a.as_slice()) * magnitude(self.matrix_b.as_slice());
+            let max_term = match &self.formulation {
+                Formulation::KoopmansBeckmann => quadratic,
+                Formulation::Linear(costs) => quadratic + magnitude(costs.as_slice()),
+                Formulation::Lawler(costs) => magnitude(costs.as_slice()),
+            };
+            assert!(fits_wide::<C>(self.size, max_term), "Costs of the instance are too large for its cost type");
         }
 
-        pub fn get_solutions_distance(&self, perm: &Vec<usize>) -> usize {
+        /// Number of real facilities placed differently than in the optimal permutation.
+        pub fn get_solutions_distance(&self, perm: &[usize]) -> usize {
             self.optimal_permutation
-                .iter().zip(perm.iter())
+                .iter().zip(perm.iter()).take(self.facilities)
                 .map(|(p1,p2)| p2 != p1)
                 .filter(|&x| x)
                 .count()
         }
 
-        pub fn get_matrix_a(&self) -> &Vec<Vec<usize>> {
+        pub fn get_matrix_a(&self) -> &Matrix<C> {
             &self.matrix_a
         }
 
-        pub fn get_matrix_b(&self) -> &Vec<Vec<usize>> {
+        pub fn get_matrix_b(&self) -> &Matrix<C> {
             &self.matrix_b
         }
 
+        pub fn get_formulation(&self) -> &Formulation<C> {
+            &self.formulation
+        }
+
+        /// Installation costs of a Koopmans–Beckmann instance with a linear term.
+        pub fn get_linear_costs(&self) -> Option<&Matrix<C>> {
+            match &self.formulation {
+                Formulation::Linear(costs) => Some(costs),
+                _ => None,
+            }
+        }
+
+        /// Both matrices of a Koopmans–Beckmann instance are symmetric, which lets the
+        /// evaluation kernels count every pair of facilities once.
+        pub fn is_symmetric(&self) -> bool {
+            !matches!(self.formulation, Formulation::Lawler(_))
+                && self.matrix_a.is_symmetric()
+                && self.matrix_b.is_symmetric()
+        }
+
         pub fn get_size(&self) -> usize {
             self.size
         }
 
-        pub fn evaluate(&self, solution: &[usize]) -> usize {
+        /// Restricts the solutions to permutations satisfying `constraints`; fails when none
+        /// does, leaving the instance unchanged.
+        pub fn set_constraints(&mut self, constraints: Constraints) -> Result<(), ConstraintError> {
+            assert_eq!(constraints.get_size(), self.size, "Constraints should match the instance size");
+            constraints.repair(&mut (0..self.size).collect::<Vec<usize>>())?;
+            self.constraints = constraints;
+            Ok(())
+        }
+
+        pub fn get_constraints(&self) -> &Constraints {
+            &self.constraints
+        }
+
+        /// Random permutation satisfying the constraints of the instance.
+        pub fn random_permutation<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<usize> {
+            self.constraints
+                .random_permutation(rng)
+                .expect("Constraints of an instance should be satisfiable")
+        }
+
+        /// Moves the facilities of `perm` that break a constraint, see `Constraints::repair`.
+        pub fn repair(&self, perm: &mut [usize]) {
+            self.constraints.repair(perm).expect("Constraints of an instance should be satisfiable");
+        }
+
+        /// Number of real facilities, at most `get_size()`.
+        pub fn get_facility_count(&self) -> usize {
+            self.facilities
+        }
+
+        /// Locations of the real facilities in `solution`, an injective map into the locations.
+        pub fn assignment<'s>(&self, solution: &'s [usize]) -> &'s [usize] {
+            &solution[..self.facilities]
+        }
+
+        /// Extends the locations of the first facilities to a full permutation, placing the
+        /// remaining positions at the unused locations in increasing order.
+        pub fn complete_assignment(&self, assignment: &[usize]) -> Vec<usize> {
+            let mut used = vec![false; self.size];
+            assignment.iter().for_each(|&k| used[k] = true);
+            let mut permutation = assignment.to_vec();
+            permutation.extend((0..self.size).filter(|&k| !used[k]));
+            permutation
+        }
+
+        /// Cost of `solution`, summed in `C::Wide`. Fails when `solution` breaks a constraint
+        /// of the instance or the total does not fit into `C`.
+        pub fn evaluate(&self, solution: &[usize]) -> Result<C, EvaluationError> {
+            self.constraints.check(solution)?;
+            Ok(C::narrow(self.evaluate_wide(solution))?)
+        }
+
+        /// Cost of `solution` in `C::Wide`, without checking the constraints.
+        pub fn evaluate_wide(&self, solution: &[usize]) -> C::Wide {
+            match &self.formulation {
+                Formulation::KoopmansBeckmann => self.evaluate_quadratic(solution),
+                Formulation::Linear(costs) => {
+                    let linear: C::Wide = self.assignment(solution).iter().enumerate().map(|(i, &k)| costs[i][k].widen()).sum();
+                    self.evaluate_quadratic(solution) + linear
+                }
+                Formulation::Lawler(costs) => (0..self.size)
+                    .flat_map(|i| (0..self.size).map(move |j| (i, j)))
+                    .map(|(i, j)| costs.get(i, j, solution[i], solution[j]).widen())
+                    .sum(),
+            }
+        }
+
+        /// The Koopmans–Beckmann term of the cost.
+        /// Dummy facilities have no flow, so only the real ones are visited.
+        fn evaluate_quadratic(&self, solution: &[usize]) -> C::Wide {
             let matrix_a = &self.matrix_a;
             let matrix_b = &self.matrix_b;
+            let (n, assignment) = (self.facilities, self.assignment(solution));
+
+            if self.is_symmetric() {
+                let mut diagonal = C::Wide::default();
+                let mut upper = C::Wide::default();
+                for (i, row_a) in matrix_a.rows().take(n).enumerate() {
+                    let row_b = matrix_b.row(assignment[i]);
+                    diagonal += row_a[i].widen() * row_b[assignment[i]].widen();
+                    upper += dot_product_permuted(&row_a[i + 1..n], row_b, &assignment[i + 1..]);
+                }
+                return diagonal + upper + upper;
+            }
 
             matrix_a
-                .iter()
-                .zip(solution.iter().map(|&index| &matrix_b[index]))
-                .map(|(row_a, row_b)| dot_product_permuted(row_a, row_b, solution))
+                .rows()
+                .zip(assignment.iter().map(|&index| matrix_b.row(index)))
+                .map(|(row_a, row_b)| dot_product_permuted(&row_a[..n], row_b, assignment))
                 .sum()
         }
     }
 
+    /// Extends every row with zeros to `size` entries and adds rows of zeros up to `size` rows.
+    fn pad<C: Cost>(mut rows: Vec<Vec<C>>, size: usize) -> Vec<Vec<C>> {
+        rows.iter_mut().for_each(|row| row.resize(size, C::default()));
+        rows.resize(size, vec![C::default(); size]);
+        rows
+    }
+
     pub struct Solver<'a> {
         instance: &'a Instance,
     }
@@ -126,24 +428,24 @@ pub mod instance {
             let a_rows_sums: Vec<usize> = self
                 .instance
                 .matrix_a
-                .iter()
+                .rows()
                 .map(|row| row.iter().sum())
                 .collect();
             let b_rows_sums: Vec<usize> = self
                 .instance
                 .matrix_b
-                .iter()
+                .rows()
                 .map(|row| row.iter().sum())
                 .collect();
 
             let a_cols_sums = self.get_columns_sum(&self.instance.matrix_a);
             let b_cols_sums = self.get_columns_sum(&self.instance.matrix_a);
 
-            let row_permutation = self.greedy_mapping(&*a_rows_sums, &*b_rows_sums);
-            let col_permutation = self.greedy_mapping(&*a_cols_sums, &*b_cols_sums);
+            let row_permutation = self.greedy_mapping(&a_rows_sums, &b_rows_sums);
+            let col_permutation = self.greedy_mapping(&a_cols_sums, &b_cols_sums);
             let cost = self.compute_cost(
-                &*row_permutation,
-                &*col_permutation,
+                &row_permutation,
+                &col_permutation,
                 &self.instance.matrix_a,
                 &self.instance.matrix_b,
             );
@@ -154,10 +456,9 @@ pub mod instance {
             (row_permutation, col_permutation, cost)
         }
 
-        fn get_columns_sum(&self, matrix: &Vec<Vec<usize>>) -> Vec<usize> {
-            let size = matrix.len();
-            (0..size)
-                .map(|col| matrix.iter().map(|row| row[col]).sum())
+        fn get_columns_sum(&self, matrix: &Matrix) -> Vec<usize> {
+            (0..matrix.get_size())
+                .map(|col| matrix.column(col).iter().sum())
                 .collect()
         }
 
@@ -177,8 +478,8 @@ pub mod instance {
             &self,
             row_permutation: &[usize],
             col_permutation: &[usize],
-            matrix_a: &Vec<Vec<usize>>,
-            matrix_b: &Vec<Vec<usize>>,
+            matrix_a: &Matrix,
+            matrix_b: &Matrix,
         ) -> usize {
             let mut cost = 0;
             for i in 0..row_permutation.len() {
@@ -198,59 +499,192 @@ pub mod instance {
         fn permutation_correct() {
             let sums_a = [2, 4, 5, 1];
             let sums_b = [1, 7, 2, 3];
-            todo!("add optimal cost to instance");
             let instance = Instance {
-                matrix_a: vec![
+                matrix_a: Matrix::new(vec![
                     vec![1, 1, 0, 0],
                     vec![0, 1, 1, 1],
                     vec![1, 0, 1, 1],
                     vec![0, 0, 1, 0],
-                ],
-                matrix_b: vec![
+                ]),
+                matrix_b: Matrix::new(vec![
                     vec![0, 1, 0, 0],
                     vec![1, 1, 1, 1],
                     vec![0, 0, 1, 1],
                     vec![0, 1, 0, 0],
-                ],
+                ]),
+                formulation: Formulation::KoopmansBeckmann,
                 size: 4,
+                facilities: 4,
                 optimal_cost: 0,
+                optimal_permutation: vec![],
+                constraints: Constraints::new(4),
             };
             let solver = Solver::new(&instance);
             let permutation = solver.greedy_mapping(&sums_a, &sums_b);
 
             assert_eq!([3, 2, 0, 1], *permutation);
         }
+
+        #[test]
+        fn symmetric_evaluation_matches_definition() {
+            let a = vec![vec![1, 4, 2], vec![4, 0, 3], vec![2, 3, 5]];
+            let b = vec![vec![2, 1, 7], vec![1, 3, 0], vec![7, 0, 1]];
+            let instance = Instance::new(a.clone(), b.clone(), 0, vec![]);
+            assert!(instance.is_symmetric());
+
+            let perm = [2, 0, 1];
+            let expected: usize = (0..3)
+                .flat_map(|i| (0..3).map(move |j| (i, j)))
+                .map(|(i, j)| a[i][j] * b[perm[i]][perm[j]])
+                .sum();
+            assert_eq!(instance.evaluate(&perm).unwrap(), expected);
+        }
+
+        #[test]
+        fn linear_and_lawler_evaluation_match_definition() {
+            let a = vec![vec![0, 2, 1], vec![3, 0, 4], vec![1, 5, 0]];
+            let b = vec![vec![0, 6, 2], vec![1, 0, 3], vec![4, 2, 0]];
+            let c = vec![vec![5, 0, 7], vec![1, 9, 2], vec![3, 3, 8]];
+            let perm = [1, 2, 0];
+            let quadratic = Instance::new(a.clone(), b.clone(), 0, vec![]).evaluate(&perm).unwrap();
+
+            let linear = Instance::with_linear_costs(a.clone(), b.clone(), c.clone(), 0, vec![]);
+            assert_eq!(linear.evaluate(&perm).unwrap(), quadratic + c[0][1] + c[1][2] + c[2][0]);
+
+            // Koopmans–Beckmann with a linear term written as a Lawler instance.
+            let n = 3;
+            let data = (0..n * n * n * n)
+                .map(|idx| {
+                    let (i, j, k, l) = (idx / (n * n * n), idx / (n * n) % n, idx / n % n, idx % n);
+                    a[i][j] * b[k][l] + if i == j && k == l { c[i][k] } else { 0 }
+                })
+                .collect();
+            let lawler = Instance::from_lawler_costs(LawlerCosts::from_flat(n, data), 0, vec![]);
+            assert_eq!(lawler.evaluate(&perm), linear.evaluate(&perm));
+        }
     }
 }
 
 pub mod solver;
 
-pub fn get_random_permutation(size: usize) -> Vec<usize> {
+pub fn get_random_permutation<R: Rng + ?Sized>(size: usize, rng: &mut R) -> Vec<usize> {
     let mut numbers: Vec<usize> = (0..size).collect();
     let mut permuatation: Vec<usize> = vec![0; size];
-    for i in 0..size - 1 {
-        let index = rand::random::<usize>() % (size - i);
-        permuatation[i] = numbers[index];
+    for (i, element) in permuatation.iter_mut().take(size - 1).enumerate() {
+        let index = rng.gen_range(0..size - i);
+        *element = numbers[index];
         numbers.swap_remove(index);
     }
     permuatation[size - 1] = numbers[0];
     permuatation
 }
 
-pub fn get_random_pair(n: usize) -> (usize, usize) {
-    let x1: usize = rand::random::<usize>() % n;
-    let x2: usize = (rand::random::<usize>() % (n - 1) + x1 + 1) % n;
+pub fn get_random_pair<R: Rng + ?Sized>(n: usize, rng: &mut R) -> (usize, usize) {
+    let x1: usize = rng.gen_range(0..n);
+    let x2: usize = (rng.gen_range(0..n - 1) + x1 + 1) % n;
     (x1, x2)
 }
 
-pub fn argsort<T: Ord>(arr: &[T], ascending: bool) -> Box<[usize]> {
+pub fn argsort<T: PartialOrd>(arr: &[T], ascending: bool) -> Box<[usize]> {
     let mut indices: Vec<usize> = (0..arr.len()).collect();
 
     if ascending {
-        indices.sort_by(|&a, &b| arr[a].cmp(&arr[b]));
+        indices.sort_by(|&a, &b| cost::cmp_costs(&arr[a], &arr[b]));
     } else {
-        indices.sort_by(|&a, &b| arr[b].cmp(&arr[a]));
+        indices.sort_by(|&a, &b| cost::cmp_costs(&arr[b], &arr[a]));
     }
 
     indices.into_boxed_slice()
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::constraints::Constraints;
+    use crate::solver::{
+        ant_colony::MaxMinAntSystemSolver, genetic::GeneticSolver, grasp::GraspSolver,
+        heuristic_solver::HeuristicSolver, linear_assignment::LinearAssignmentSolver,
+        local_search::{greedy::GreedySolver, steepest::SteepestSolver},
+        random_search::RandomSearchSolver, random_walk::RandomWalkSolver,
+        simulated_annealing::SimulatedAnnealingSolver, tabu_search::TabuSearchSolver,
+        variable_neighbourhood::VariableNeighbourhoodSolver, Solver,
+    };
+
+    #[test]
+    fn same_seed_reproduces_runs() {
+        let instance = Instance::new(
+            vec![vec![0, 3, 1, 4], vec![3, 0, 2, 5], vec![1, 2, 0, 6], vec![4, 5, 6, 0]],
+            vec![vec![0, 7, 2, 1], vec![7, 0, 3, 8], vec![2, 3, 0, 4], vec![1, 8, 4, 0]],
+            0,
+            vec![0, 1, 2, 3],
+        );
+        let mut solver = GreedySolver::new(&instance, u128::MAX);
+
+        let first = measure_time(&mut solver, &instance, "test", 5, 7, 0);
+        let second = measure_time(&mut solver, &instance, "test", 5, 7, 0);
+
+        assert_eq!(first.len(), second.len());
+        for (a, b) in first.iter().zip(second.iter()) {
+            assert_eq!(a.seed, b.seed);
+            assert_eq!(a.initial_cost, b.initial_cost);
+            assert_eq!(a.cost, b.cost);
+            assert_eq!(a.evaluated_solutions, b.evaluated_solutions);
+        }
+    }
+
+    #[test]
+    fn measures_fractional_costs() {
+        let instance: Instance<f64> = Instance::new(
+            vec![vec![0.0, 0.5, 1.5], vec![0.5, 0.0, 2.0], vec![1.5, 2.0, 0.0]],
+            vec![vec![0.0, 1.0, 0.25], vec![1.0, 0.0, 3.0], vec![0.25, 3.0, 0.0]],
+            0.0,
+            vec![],
+        );
+        let mut solver = TabuSearchSolver::new(&instance, 100, u128::MAX);
+
+        let metrics = measure_time(&mut solver, &instance, "fractional", 3, 5, 2.0);
+
+        for run in &metrics {
+            assert_eq!(instance.evaluate(&run.permutation).unwrap(), run.cost);
+            assert_eq!(optimality_gap(run.cost, 2.0), run.gap);
+        }
+    }
+
+    #[test]
+    fn solvers_respect_constraints() {
+        let n = 8;
+        let flows: Vec<Vec<usize>> = (0..n).map(|i: usize| (0..n).map(|j| (i * 7 + j * 3) % 10).collect()).collect();
+        let distances: Vec<Vec<usize>> = (0..n).map(|i: usize| (0..n).map(|j| i.abs_diff(j)).collect()).collect();
+        let mut instance = Instance::new(flows, distances, 0, vec![]);
+        let mut constraints = Constraints::new(n);
+        constraints.fix(0, 7);
+        constraints.fix(3, 2);
+        (1..n).for_each(|facility| constraints.forbid(facility, facility));
+        instance.set_constraints(constraints).unwrap();
+
+        let time = 2_000_000;
+        let mut solvers: Vec<Box<dyn Solver + '_>> = vec![
+            Box::new(GreedySolver::new(&instance, time)),
+            Box::new(SteepestSolver::new(&instance, time)),
+            Box::new(RandomSearchSolver::new(&instance, 100, time)),
+            Box::new(RandomWalkSolver::new(&instance, 100, time)),
+            Box::new(SimulatedAnnealingSolver::new(&instance, time)),
+            Box::new(TabuSearchSolver::new(&instance, 500, time)),
+            Box::new(VariableNeighbourhoodSolver::new(&instance, time, 3)),
+            Box::new(GeneticSolver::new(&instance, time, Default::default())),
+            Box::new(GraspSolver::new(&instance, time, Default::default())),
+            Box::new(MaxMinAntSystemSolver::new(&instance, time, Default::default())),
+            Box::new(HeuristicSolver::new(&instance)),
+            Box::new(LinearAssignmentSolver::new(&instance)),
+        ];
+        let mut rng = StdRng::seed_from_u64(18);
+        for solver in solvers.iter_mut() {
+            let start = instance.random_permutation(&mut rng);
+            let solution = solver.solve(start, &mut rng).unwrap();
+            assert!(instance.get_constraints().is_feasible(&solution.permutation), "{}", solver.get_name());
+        }
+        assert!(instance.evaluate(&(0..n).collect::<Vec<_>>()).is_err());
+    }
+}
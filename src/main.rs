@@ -1,122 +1,18 @@
-use std::io::BufRead;
-use quadratic_assignment_problem::io::experiments::{initial_quality_experiment, run_alg_with_time_constrains, run_all_algorithms, run_all_algorithms_with_time_constrains};
-use quadratic_assignment_problem::io::save_metrics_to_csv;
-// use quadratic_assignment_problem::measure_time;
-// use quadratic_assignment_problem::solver::random_search::RandomSearchSolver;
-// use quadratic_assignment_problem::solver::random_walk::RandomWalkSolver;
-// use quadratic_assignment_problem::solver::simulated_annealing::SimulatedAnnealingSolver;
-use quadratic_assignment_problem::solver::{heuristic_solver, local_search, Solver};
-use quadratic_assignment_problem::{get_random_permutation, io::InstanceReader, measure_time};
-use quadratic_assignment_problem::solver::local_search::greedy::GreedySolver;
-use quadratic_assignment_problem::solver::local_search::steepest::SteepestSolver;
-use quadratic_assignment_problem::solver::random_walk::RandomWalkSolver;
+use quadratic_assignment_problem::io::{config::ExperimentConfig, experiments::run_experiment};
 
-fn main() {
-    let chr_instances = ["chr12a", "chr15a", "chr18a", "chr20a", "chr22a", "chr25a"];
-    let chr_limits = vec![2_500_000; chr_instances.len()];
-    let lipa_instances = ["lipa20a", "lipa30a", "lipa40a", "lipa50a", "lipa60a", "lipa70a", "lipa80a", "lipa90a"];
-    let lipa_limits = [1e6 as u128, 50e7 as u128, 1e8 as u128, 2e8 as u128, 5e8 as u128, 7.5e8 as u128, 1.2e9 as u128, 2e9 as u128];
-
-    // let solution = solver.solve(get_random_permutation(instance.size)).unwrap();
-    // let instance_reader = InstanceReader::new("qap/instances");
-    // let instance = instance_reader
-    //     .read_instance(instances[1])
-    //     .expect("Failed to read instance file");
-
-    // assert_eq!(instance.evaluate(instance.optimal_permutation.as_ref()), instance.optimal_cost);
-
-    // let content = instance_reader.read_optimal_solution(instances[1]).unwrap();
-
-    run_all_algorithms(&chr_instances, "chr2", &chr_limits);
-
-    // let initial_quality_instances = ["chr12a", "chr20a", "lipa20a"];
-    // let repetition_experiment_instances = ["tai12b", "tai100b"];
-    // initial_quality_experiment(&repetition_experiment_instances, "repetition", 300);
+const DEFAULT_CONFIG: &str = "experiments/chr.toml";
 
-    // let mut limits: Vec<u128> = Vec::new();
-    // for exp in 4..=10 {
-    //     let num = 5 * 10u64.pow(exp);
-    //     limits.push(num as u128);
-    // }
-    //
-    // let current_instance = instances[5];
-    //
-    // let instance_reader = InstanceReader::new("qap/instances");
-    // let instance = instance_reader
-    //     .read_instance(instances[1])
-    //     .expect("Failed to read instance file");
-    //
-    // let mut steepest_solver = Box::new(SteepestSolver::new(
-    //     &instance, u128::MAX,
-    // ));
-    //
-    // let mut greedy_solver = Box::new(GreedySolver::new(
-    //     &instance, u128::MAX,
-    // ));
-    //
-    // let metrics1 = measure_time(&mut *steepest_solver, &instance, current_instance, 10);
-    // let metrics2 = measure_time(&mut *greedy_solver, &instance, current_instance, 10);
-    // let _ = save_metrics_to_csv(&format!("output/{}/{}.csv", current_instance ,steepest_solver.get_name()), &metrics1);
-    // let _ = save_metrics_to_csv(&format!("output/{}/{}.csv", current_instance, greedy_solver.get_name()), &metrics2);
-    //
-
-    // run_all_algorithms_with_time_constrains();
-    // run_alg_with_time_constrains("GreedySolver", instances[0], &limits);
-    // for mut solver in solvers {
-    // println!(
-    //     "{:?}:\ttotal time: {:?}\t avg time: {:?}\t avg cost: {:?}\t iterations: {:?}",
-    //     solver.get_name(),
-    //     total_time,
-    //     (total_time / iterations as u128),
-    //     avg_cost,
-    //     iterations
-    // );
-    // }
-
-    // let mut random_search_solver = RandomSearchSolver::new(&instance, 10_000);
-    // let random_search_result = random_search_solver.solve(perm).unwrap();
-    // println!("random search result: {:?}", random_search_result);
-    // println!(
-    //     "cost: {:?}",
-    //     instance.evaluate(random_search_result.as_ref())
-    // );
-    //
-    // let mut random_walk_solver = RandomWalkSolver::new(&instance, 10_000_000);
-    // let random_walk_result = random_walk_solver.solve(perm).unwrap();
-    // println!("random walk result: {:?}", random_walk_result);
-    // println!("cost: {:?}", instance.evaluate(random_walk_result.as_ref()));
-    //
-    // let mut greedy_solver = greedy_solver::GreedySolver::new(&instance);
-    // let greedy_result = greedy_solver.solve(perm).unwrap();
-    // println!("greedy result: {:?}", greedy_result);
-    // println!("cost: {:?}", instance.evaluate(greedy_result.as_ref()));
-
-    // let (total_time, iterations) = measure_time(local_search, &instance, perm);
-    // println!(
-    //     "total time: {:?}\t avg time: {:?}\t iterations: {:?}",
-    //     total_time,
-    //     (total_time / iterations as u128),
-    //     iterations
-    // );
-    // let solution = local_search(&instance, perm);
-    // let optimum_perm = [6, 4, 11, 1, 0, 2, 8, 10, 9, 5, 7, 3];
-    // let optimum_cost = instance.evaluate(&optimum_perm);
-    // println!("optimum perm: {:?}\t cost: {optimum_cost}", optimum_perm);
-
-    // let (total_time, iterations) = measure_time(simulated_annealing, &instance, perm);
-    // println!(
-    //     "total time: {:?}\t avg time: {:?}\t iterations: {:?}",
-    //     total_time / 1_000_000,
-    //     (total_time / 1_000_000 / iterations as u128),
-    //     iterations,
-    // );
-    // let solution = simulated_annealing(&instance, perm);
-    // let cost = instance.evaluate(&solution);
-    // println!("solution perm: {:?}\t cost: {cost}", solution);
-    // let solver = instance::Solver::new(&instance);
-    // let solution = solver.solve();
-    //
-    // println!("{:?}", solution);
-    // println!("{:?}", get_random_pair(2));
-    // println!("{:?}", get_random_permutation(5));
+/// Runs the experiment described by the TOML or JSON file given as the first argument.
+///
+/// Usage: `cargo run --release -- [experiments/chr.toml]`
+fn main() {
+    let path = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_CONFIG.to_string());
+    let config = ExperimentConfig::from_file(&path).unwrap_or_else(|error| {
+        eprintln!("Failed to read {}: {}", path, error);
+        std::process::exit(1);
+    });
+    if let Err(error) = run_experiment(&config) {
+        eprintln!("Experiment {} failed: {}", path, error);
+        std::process::exit(1);
+    }
 }
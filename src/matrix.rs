@@ -0,0 +1,148 @@
+use std::ops::Index;
+
+use crate::cost::Cost;
+
+/// Square matrix stored row-major in one contiguous buffer, together with its transpose so
+/// that columns are contiguous as well. `matrix[i]` is the `i`-th row, so `matrix[i][j]`
+/// reads like with nested vectors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix<C: Cost = usize> {
+    size: usize,
+    data: Vec<C>,
+    transposed: Vec<C>,
+    symmetric: bool,
+}
+
+impl<C: Cost> Matrix<C> {
+    /// Builds the matrix from its rows; panics if it is not square.
+    pub fn new(rows: Vec<Vec<C>>) -> Matrix<C> {
+        let size = rows.len();
+        assert!(rows.iter().all(|row| row.len() == size), "Matrix should be square");
+        Matrix::from_flat(size, rows.into_iter().flatten().collect())
+    }
+
+    /// Builds the matrix from `size * size` entries in row-major order.
+    pub fn from_flat(size: usize, data: Vec<C>) -> Matrix<C> {
+        assert_eq!(data.len(), size * size, "Matrix should have size * size entries");
+        let transposed: Vec<C> = (0..size * size).map(|idx| data[(idx % size) * size + idx / size]).collect();
+        let symmetric = data == transposed;
+        Matrix {
+            size,
+            data,
+            transposed,
+            symmetric,
+        }
+    }
+
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        self.symmetric
+    }
+
+    pub fn row(&self, i: usize) -> &[C] {
+        &self.data[i * self.size..(i + 1) * self.size]
+    }
+
+    /// The `j`-th column, contiguous in memory.
+    pub fn column(&self, j: usize) -> &[C] {
+        &self.transposed[j * self.size..(j + 1) * self.size]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[C]> {
+        self.data.chunks_exact(self.size.max(1))
+    }
+
+    /// Entries in row-major order.
+    pub fn as_slice(&self) -> &[C] {
+        &self.data
+    }
+
+    pub fn to_rows(&self) -> Vec<Vec<C>> {
+        self.rows().map(|row| row.to_vec()).collect()
+    }
+}
+
+impl<C: Cost> Index<usize> for Matrix<C> {
+    type Output = [C];
+
+    fn index(&self, i: usize) -> &[C] {
+        self.row(i)
+    }
+}
+
+impl<C: Cost> From<Vec<Vec<C>>> for Matrix<C> {
+    fn from(rows: Vec<Vec<C>>) -> Matrix<C> {
+        Matrix::new(rows)
+    }
+}
+
+/// Four-index cost array of a Lawler QAP, where `costs.get(i, j, k, l)` is paid when facility
+/// `i` is at location `k` and facility `j` at location `l`. Entries are stored flat, one
+/// `n * n` block over `(k, l)` for every pair of facilities `(i, j)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LawlerCosts<C: Cost = usize> {
+    size: usize,
+    data: Vec<C>,
+}
+
+impl<C: Cost> LawlerCosts<C> {
+    /// Builds the array from `size^4` entries, index `((i * n + j) * n + k) * n + l`.
+    pub fn from_flat(size: usize, data: Vec<C>) -> LawlerCosts<C> {
+        assert_eq!(data.len(), size.pow(4), "Lawler costs should have size^4 entries");
+        LawlerCosts { size, data }
+    }
+
+    /// Builds the array from the `(k, l)` blocks of every pair `(i, j)` in row-major order.
+    pub fn from_blocks(size: usize, blocks: Vec<Vec<Vec<C>>>) -> LawlerCosts<C> {
+        assert_eq!(blocks.len(), size * size, "Lawler costs should have size^2 blocks");
+        LawlerCosts::from_flat(size, blocks.into_iter().flatten().flatten().collect())
+    }
+
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get(&self, i: usize, j: usize, k: usize, l: usize) -> C {
+        self.block(i, j)[k * self.size + l]
+    }
+
+    /// Costs of facilities `i` and `j` over all pairs of locations, row-major in `(k, l)`.
+    pub fn block(&self, i: usize, j: usize) -> &[C] {
+        let start = (i * self.size + j) * self.size * self.size;
+        &self.data[start..start + self.size * self.size]
+    }
+
+    pub fn as_slice(&self) -> &[C] {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_columns_and_symmetry() {
+        let matrix = Matrix::new(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]);
+        assert_eq!(matrix[1][2], 6);
+        assert_eq!(matrix.row(2), &[7, 8, 9]);
+        assert_eq!(matrix.column(0), &[1, 4, 7]);
+        assert!(!matrix.is_symmetric());
+        assert_eq!(matrix.to_rows()[1], vec![4, 5, 6]);
+
+        let symmetric = Matrix::new(vec![vec![0, 2, 3], vec![2, 0, 6], vec![3, 6, 0]]);
+        assert!(symmetric.is_symmetric());
+    }
+
+    #[test]
+    fn lawler_costs_are_indexed_by_blocks() {
+        let blocks = (0..4).map(|pair| vec![vec![10 * pair, 10 * pair + 1], vec![10 * pair + 2, 10 * pair + 3]]);
+        let costs = LawlerCosts::from_blocks(2, blocks.collect());
+        assert_eq!(costs.get(0, 1, 1, 0), 12);
+        assert_eq!(costs.get(1, 1, 0, 1), 31);
+        assert_eq!(costs.block(1, 0), &[20, 21, 22, 23]);
+    }
+}
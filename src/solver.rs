@@ -1,5 +1,12 @@
-use crate::instance::Instance;
-
+use crate::{
+    constraints::ConstraintError,
+    cost::{Cost, CostOverflow},
+    instance::{EvaluationError, Formulation, Instance},
+};
+use monitor::Monitor;
+use trace::TracePoint;
+
+use rand::rngs::StdRng;
 use std::fmt;
 
 type Result<T> = std::result::Result<T, SolvingError>;
@@ -15,18 +22,48 @@ impl fmt::Display for SolvingError {
     }
 }
 
+impl From<ConstraintError> for SolvingError {
+    fn from(error: ConstraintError) -> SolvingError {
+        SolvingError {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<CostOverflow> for SolvingError {
+    fn from(error: CostOverflow) -> SolvingError {
+        SolvingError {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<EvaluationError> for SolvingError {
+    fn from(error: EvaluationError) -> SolvingError {
+        SolvingError {
+            message: error.to_string(),
+        }
+    }
+}
+
 pub struct Solution {
     pub permutation: Vec<usize>,
     pub evaluations: usize,
     pub solution_changes: usize,
+    /// Convergence trace of the run; empty unless the monitor of the solver enables tracing.
+    pub trace: Vec<TracePoint>,
 }
 
-pub trait Solver {
-    fn solve(&mut self, initial_solution: Vec<usize>) -> Result<Solution>;
+pub trait Solver<C: Cost = usize> {
+    /// Solves the instance starting from `initial_solution`. Every random decision is drawn
+    /// from `rng`, so the same seed and starting permutation reproduce the same run.
+    fn solve(&mut self, initial_solution: Vec<usize>, rng: &mut StdRng) -> Result<Solution>;
     fn get_name(&self) -> String;
-    fn get_instance(&self) -> &Instance;
+    fn get_instance(&self) -> &Instance<C>;
     fn set_time_limit(&mut self, time_limit: u128);
     fn get_time_limit(&self) -> u128;
+    /// Traces, observes or cancels the following runs, see `Monitor`.
+    fn set_monitor(&mut self, monitor: Monitor);
 }
 
 // fn compute_cost(instance: &Instance, permutation: &[usize]) -> usize {
@@ -55,83 +92,146 @@ fn calculate_swap_indices(n: isize, k: isize) -> (usize, usize) {
     (i as usize, j as usize)
 }
 
-fn compute_num_neighbours(n: usize) -> usize {
-    n * (n - 1) / 2
+/// Cost decrease of moving the facilities at `positions` to `new_locations` (which must be a
+/// rearrangement of their current locations), computed in O(n * positions.len()).
+pub fn eval_diff_reassign<C: Cost>(
+    instance: &Instance<C>,
+    perm: &[usize],
+    positions: &[usize],
+    new_locations: &[usize],
+) -> C::Wide {
+    let moved = |v: usize| positions.iter().position(|&u| u == v);
+    eval_diff_relocated(
+        instance,
+        perm,
+        positions.iter().copied(),
+        |v| moved(v).is_some(),
+        |v| moved(v).map_or(perm[v], |idx| new_locations[idx]),
+    )
 }
 
-fn eval_diff(instance: &Instance, perm: &[usize], neighbour_idx: usize) -> i32 {
-    let n = perm.len();
-    let (swap_index_0, swap_index_1) = calculate_swap_indices(n as isize, neighbour_idx as isize);
-
-    let mut before_swap = dot_product_permuted(
-        &instance.matrix_a[swap_index_0],
-        &instance.matrix_b[perm[swap_index_0]],
-        perm,
-    ) + dot_product_permuted(
-        &instance.matrix_a[swap_index_1],
-        &instance.matrix_b[perm[swap_index_1]],
-        perm,
-    );
+/// Same as `eval_diff_reassign` for moves whose changed positions are easier to describe by
+/// closures: `moved(v)` tells whether position `v` is among `positions` and `new_location(v)`
+/// gives its location after the move.
+fn eval_diff_relocated<C: Cost>(
+    instance: &Instance<C>,
+    perm: &[usize],
+    positions: impl Iterator<Item = usize>,
+    moved: impl Fn(usize) -> bool,
+    new_location: impl Fn(usize) -> usize,
+) -> C::Wide {
+    let mut increase = C::Wide::default();
+    for u in positions {
+        increase += match instance.get_formulation() {
+            Formulation::KoopmansBeckmann => relocation_increase(instance, perm, u, &moved, &new_location),
+            Formulation::Linear(costs) => {
+                costs[u][new_location(u)].widen() - costs[u][perm[u]].widen()
+                    + relocation_increase(instance, perm, u, &moved, &new_location)
+            }
+            Formulation::Lawler(costs) => {
+                let (old_u, new_u) = (perm[u], new_location(u));
+                let mut increase = C::Wide::default();
+                for (v, &old_v) in perm.iter().enumerate() {
+                    increase += costs.get(u, v, new_u, new_location(v)).widen() - costs.get(u, v, old_u, old_v).widen();
+                    if !moved(v) {
+                        increase += costs.get(v, u, old_v, new_u).widen() - costs.get(v, u, old_v, old_u).widen();
+                    }
+                }
+                increase
+            }
+        };
+    }
+    -increase
+}
 
-    let mut after_swap = dot_product_permuted_with_swap(
-        &instance.matrix_a[swap_index_0],
-        &instance.matrix_b[perm[swap_index_1]],
-        perm,
-        swap_index_0,
-        swap_index_1,
-    ) + dot_product_permuted_with_swap(
-        &instance.matrix_a[swap_index_1],
-        &instance.matrix_b[perm[swap_index_0]],
-        perm,
-        swap_index_0,
-        swap_index_1,
-    );
-
-    for i in 0..n {
-        if i != swap_index_0 && i != swap_index_1 {
-            let row_a = &instance.matrix_a[i];
-            let row_b = &instance.matrix_b[perm[i]];
-            before_swap += row_a[swap_index_0] * row_b[perm[swap_index_0]]
-                + row_a[swap_index_1] * row_b[perm[swap_index_1]];
-
-            after_swap += row_a[swap_index_1] * row_b[perm[swap_index_0]]
-                + row_a[swap_index_0] * row_b[perm[swap_index_1]];
+/// Change of the Koopmans–Beckmann term caused by the interactions of the facility at
+/// position `u` with every real facility, counting those between two moved facilities once.
+fn relocation_increase<C: Cost>(
+    instance: &Instance<C>,
+    perm: &[usize],
+    u: usize,
+    moved: impl Fn(usize) -> bool,
+    new_location: impl Fn(usize) -> usize,
+) -> C::Wide {
+    let a = &instance.matrix_a;
+    let b = &instance.matrix_b;
+    let symmetric = instance.is_symmetric();
+
+    let mut increase = C::Wide::default();
+    if u >= instance.get_facility_count() {
+        // Dummy facilities have no flow.
+        return increase;
+    }
+    let (old_u, new_u) = (perm[u], new_location(u));
+    let (row_a, row_b_old, row_b_new) = (a.row(u), b.row(old_u), b.row(new_u));
+    let (column_a, column_b_old, column_b_new) = (a.column(u), b.column(old_u), b.column(new_u));
+    for (v, &old_v) in instance.assignment(perm).iter().enumerate() {
+        let new_v = new_location(v);
+        let outgoing = row_a[v].widen() * (row_b_new[new_v].widen() - row_b_old[old_v].widen());
+        increase += outgoing;
+        if !moved(v) {
+            // With symmetric matrices the incoming interaction equals the outgoing one.
+            increase += if symmetric {
+                outgoing
+            } else {
+                column_a[v].widen() * (column_b_new[old_v].widen() - column_b_old[old_v].widen())
+            };
         }
     }
+    increase
+}
 
-    before_swap as i32 - after_swap as i32
+/// Rotates three facilities: `i` takes the location of `j`, `j` that of `k` and `k` that of `i`.
+pub fn rotate_three(perm: &mut [usize], i: usize, j: usize, k: usize) {
+    let location_i = perm[i];
+    perm[i] = perm[j];
+    perm[j] = perm[k];
+    perm[k] = location_i;
 }
 
-pub fn dot_product_permuted(row_a: &[usize], row_b: &[usize], perm: &[usize]) -> usize {
-    row_a
-        .iter()
-        .zip(perm.iter().map(|&index| row_b[index]))
-        .map(|(&a, b)| a * b)
-        .sum()
+/// `sum_k row_a[k] * row_b[perm[k]]`, summed in four independent lanes so that consecutive
+/// products do not wait for each other.
+pub fn dot_product_permuted<C: Cost>(row_a: &[C], row_b: &[C], perm: &[usize]) -> C::Wide {
+    let len = row_a.len().min(perm.len());
+    let (chunks_a, chunks_perm) = (row_a[..len].chunks_exact(4), perm[..len].chunks_exact(4));
+    let mut lanes = [C::Wide::default(); 4];
+    for (a, k) in chunks_a.remainder().iter().zip(chunks_perm.remainder()) {
+        lanes[0] += a.widen() * row_b[*k].widen();
+    }
+    for (a, k) in chunks_a.zip(chunks_perm) {
+        lanes[0] += a[0].widen() * row_b[k[0]].widen();
+        lanes[1] += a[1].widen() * row_b[k[1]].widen();
+        lanes[2] += a[2].widen() * row_b[k[2]].widen();
+        lanes[3] += a[3].widen() * row_b[k[3]].widen();
+    }
+    (lanes[0] + lanes[1]) + (lanes[2] + lanes[3])
 }
 
-pub fn dot_product_permuted_with_swap(
-    row_a: &[usize],
-    row_b: &[usize],
+pub fn dot_product_permuted_with_swap<C: Cost>(
+    row_a: &[C],
+    row_b: &[C],
     perm: &[usize],
     swap_idx_0: usize,
     swap_idx_1: usize,
-) -> usize {
-    let mut sum = 0;
-    for i in 0..row_a.len() {
-        if i == swap_idx_0 {
-            sum += row_a[i] * row_b[perm[swap_idx_1]];
-        } else if i == swap_idx_1 {
-            sum += row_a[i] * row_b[perm[swap_idx_0]];
-        } else {
-            sum += row_a[i] * row_b[perm[i]];
-        }
-    }
-    sum
+) -> C::Wide {
+    let (a_0, a_1) = (row_a[swap_idx_0].widen(), row_a[swap_idx_1].widen());
+    let (b_0, b_1) = (row_b[perm[swap_idx_0]].widen(), row_b[perm[swap_idx_1]].widen());
+    dot_product_permuted(row_a, row_b, perm) + (a_1 - a_0) * (b_0 - b_1)
 }
+pub mod ant_colony;
+pub mod branch_and_bound;
+pub mod delta_matrix;
+pub mod genetic;
+pub mod grasp;
 pub mod heuristic_solver;
+pub mod iterated_local_search;
+pub mod linear_assignment;
 pub mod local_search;
+pub mod monitor;
+pub mod neighbourhood;
 pub mod random_search;
 pub mod random_walk;
 pub mod simulated_annealing;
 pub mod tabu_search;
+pub mod trace;
+pub mod variable_neighbourhood;
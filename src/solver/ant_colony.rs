@@ -0,0 +1,244 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+use crate::{cost::Cost, instance::Instance};
+
+use super::{
+    local_search::LocalSearch,
+    monitor::Monitor,
+    trace::Tracer,
+    Solution, Solver, SolvingError,
+};
+
+// As in Stützle and Hoos, every few iterations the global best ant deposits pheromone
+// instead of the iteration best one.
+const GLOBAL_BEST_UPDATE_PERIOD: usize = 5;
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AntSystemConfig {
+    pub ants: usize,
+    /// Fraction of pheromone evaporating after every iteration.
+    pub evaporation: f64,
+    /// Upper pheromone bound; `None` uses `1 / (evaporation * best_cost)`.
+    pub tau_max: Option<f64>,
+    /// `tau_min / tau_max`; `None` uses `1 / (2n)`.
+    pub tau_ratio: Option<f64>,
+    /// Iterations without a new best solution after which the pheromone is reset.
+    pub restart_after: usize,
+    pub local_search: LocalSearch,
+}
+
+impl Default for AntSystemConfig {
+    fn default() -> Self {
+        AntSystemConfig {
+            ants: 5,
+            evaporation: 0.2,
+            tau_max: None,
+            tau_ratio: None,
+            restart_after: 50,
+            local_search: LocalSearch::Greedy,
+        }
+    }
+}
+
+/// Max–Min Ant System (Stützle and Hoos) for the QAP. Ants place facilities in random
+/// order, choosing locations proportionally to the facility×location pheromone, and every
+/// constructed assignment is improved by local search in the swap neighbourhood.
+pub struct MaxMinAntSystemSolver<'a, C: Cost = usize> {
+    instance: &'a Instance<C>,
+    max_time: u128,
+    monitor: Monitor,
+    config: AntSystemConfig,
+}
+
+impl<'a, C: Cost> MaxMinAntSystemSolver<'a, C> {
+    pub fn new(instance: &Instance<C>, max_time: u128, config: AntSystemConfig) -> MaxMinAntSystemSolver<'_, C> {
+        MaxMinAntSystemSolver {
+            instance,
+            max_time,
+            monitor: Monitor::default(),
+            config,
+        }
+    }
+
+    fn tau_bounds(&self, best_cost: C) -> (f64, f64) {
+        let n = self.instance.size as f64;
+        let tau_max = self
+            .config
+            .tau_max
+            .unwrap_or_else(|| 1.0 / (self.config.evaporation * best_cost.to_f64().max(1.0)));
+        let tau_ratio = self.config.tau_ratio.unwrap_or(1.0 / (2.0 * n));
+        (tau_max * tau_ratio, tau_max)
+    }
+
+    /// Evaporates the pheromone and lets the ant that found `depositing` deposit on its
+    /// assignments, keeping every trail within `[tau_min, tau_max]`.
+    fn update_pheromone(&self, pheromone: &mut [Vec<f64>], depositing: &[usize], cost: C, (tau_min, tau_max): (f64, f64)) {
+        let deposit = 1.0 / cost.to_f64().max(1.0);
+        for (facility, row) in pheromone.iter_mut().enumerate() {
+            for (location, tau) in row.iter_mut().enumerate() {
+                *tau *= 1.0 - self.config.evaporation;
+                if depositing[facility] == location {
+                    *tau += deposit;
+                }
+                *tau = tau.clamp(tau_min, tau_max);
+            }
+        }
+    }
+
+    fn construct(&self, pheromone: &[Vec<f64>], rng: &mut StdRng) -> Vec<usize> {
+        let n = self.instance.size;
+        let mut facilities: Vec<usize> = (0..n).collect();
+        facilities.shuffle(rng);
+        let mut free_locations: Vec<usize> = (0..n).collect();
+        let mut perm = vec![0; n];
+
+        // Forbidden locations get no pheromone; a facility left with none of its allowed
+        // locations is moved by the final repair.
+        let constraints = self.instance.get_constraints();
+        let weight = |facility: usize, k: usize| if constraints.allows(facility, k) { pheromone[facility][k] } else { 0.0 };
+        for facility in facilities {
+            let total: f64 = free_locations.iter().map(|&k| weight(facility, k)).sum();
+            let mut threshold = rng.gen::<f64>() * total;
+            let mut chosen = free_locations.len() - 1;
+            for (idx, &k) in free_locations.iter().enumerate() {
+                threshold -= weight(facility, k);
+                if threshold <= 0.0 && weight(facility, k) > 0.0 {
+                    chosen = idx;
+                    break;
+                }
+            }
+            perm[facility] = free_locations.swap_remove(chosen);
+        }
+        self.instance.repair(&mut perm);
+        perm
+    }
+}
+
+impl<'a, C: Cost> Solver<C> for MaxMinAntSystemSolver<'a, C> {
+    fn solve(
+        &mut self,
+        initial_solution: Vec<usize>,
+        rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        self.instance.get_constraints().check(&initial_solution)?;
+        let n = self.instance.size;
+        let start = std::time::Instant::now();
+        let time_left = |start: &std::time::Instant| self.max_time.saturating_sub(start.elapsed().as_nanos());
+        let mut tracer = Tracer::new(&self.monitor);
+
+        let descent = self.config.local_search.run(self.instance, initial_solution, time_left(&start), tracer.get_cancellation(), rng);
+        let mut evaluations = descent.evaluations + 1;
+        let mut solution_changes = 0;
+        let mut best_cost = self.instance.evaluate(&descent.permutation)?;
+        let mut best = descent.permutation;
+        tracer.record(evaluations, best_cost.to_f64(), best_cost.to_f64());
+
+        let (_, tau_max) = self.tau_bounds(best_cost);
+        let mut pheromone = vec![vec![tau_max; n]; n];
+        let mut iteration = 0;
+        let mut iterations_without_improvement = 0;
+
+        while start.elapsed().as_nanos() < self.max_time && !tracer.is_cancelled() {
+            let mut iteration_best: Option<(Vec<usize>, C)> = None;
+            for _ in 0..self.config.ants.max(1) {
+                let perm = self.construct(&pheromone, rng);
+                let descent = self.config.local_search.run(self.instance, perm, time_left(&start), tracer.get_cancellation(), rng);
+                evaluations += descent.evaluations + 1;
+                let cost = self.instance.evaluate(&descent.permutation)?;
+                if iteration_best.as_ref().is_none_or(|(_, cheapest)| cost < *cheapest) {
+                    iteration_best = Some((descent.permutation, cost));
+                }
+            }
+            let (iteration_best, iteration_best_cost) = iteration_best.expect("At least one ant should run");
+
+            if iteration_best_cost < best_cost {
+                best_cost = iteration_best_cost;
+                best.clone_from(&iteration_best);
+                solution_changes += 1;
+                iterations_without_improvement = 0;
+            } else {
+                iterations_without_improvement += 1;
+            }
+
+            let (tau_min, tau_max) = self.tau_bounds(best_cost);
+            if iterations_without_improvement >= self.config.restart_after {
+                pheromone.iter_mut().for_each(|row| row.fill(tau_max));
+                iterations_without_improvement = 0;
+            } else {
+                let (depositing, depositing_cost) = if iteration % GLOBAL_BEST_UPDATE_PERIOD == 0 {
+                    (&best, best_cost)
+                } else {
+                    (&iteration_best, iteration_best_cost)
+                };
+                self.update_pheromone(&mut pheromone, depositing, depositing_cost, (tau_min, tau_max));
+            }
+            iteration += 1;
+            tracer.record(evaluations, iteration_best_cost.to_f64(), best_cost.to_f64());
+        }
+
+        Ok(Solution {
+            permutation: best,
+            evaluations,
+            solution_changes,
+            trace: tracer.finish(evaluations, best_cost.to_f64(), best_cost.to_f64()),
+        })
+    }
+
+    fn get_name(&self) -> String {
+        "MaxMinAntSystemSolver".to_string()
+    }
+
+    fn get_instance(&self) -> &Instance<C> {
+        self.instance
+    }
+
+    fn set_time_limit(&mut self, time_limit: u128) {
+        self.max_time = time_limit;
+    }
+
+    fn get_time_limit(&self) -> u128 {
+        self.max_time
+    }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::generator::{li_pardalos, LipaKind};
+
+    #[test]
+    fn pheromone_stays_within_the_bounds() {
+        let instance = li_pardalos(10, LipaKind::A, 3);
+        let mut rng = StdRng::seed_from_u64(10);
+        for evaporation in [0.02, 0.2, 0.9] {
+            let config = AntSystemConfig { evaporation, ..AntSystemConfig::default() };
+            let solver = MaxMinAntSystemSolver::new(&instance, u128::MAX, config);
+            let (tau_min, tau_max) = solver.tau_bounds(instance.optimal_cost);
+            assert!((tau_min * 2.0 * 10.0 - tau_max).abs() < 1e-12 * tau_max);
+
+            let mut pheromone = vec![vec![tau_max; 10]; 10];
+            for iteration in 0..200 {
+                // Now and then a cheap ant deposits far more than `tau_max`.
+                let (depositing, cost) = if iteration % 7 == 0 {
+                    (instance.optimal_permutation.clone(), 1)
+                } else {
+                    let perm = instance.random_permutation(&mut rng);
+                    let cost = instance.evaluate(&perm).unwrap();
+                    (perm, cost)
+                };
+                solver.update_pheromone(&mut pheromone, &depositing, cost, (tau_min, tau_max));
+                assert!(pheromone.iter().flatten().all(|&tau| (tau_min..=tau_max).contains(&tau)));
+            }
+            // Evaporation drives the trails no ant keeps depositing on down to `tau_min`.
+            assert!(pheromone.iter().flatten().any(|&tau| tau == tau_min));
+        }
+    }
+}
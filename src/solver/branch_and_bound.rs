@@ -0,0 +1,273 @@
+use rand::rngs::StdRng;
+
+use crate::{
+    bounds,
+    cost::{cmp_costs, Cost},
+    instance::Instance,
+};
+
+use super::{
+    monitor::Monitor,
+    trace::Tracer,
+    Solution, Solver, SolvingError,
+};
+
+/// Exact solver for small instances (n up to about 15): depth-first branch and bound that
+/// assigns facilities one by one and prunes partial assignments with the Gilmore–Lawler
+/// bound.
+///
+/// The starting permutation is used as the first incumbent. When the time limit interrupts
+/// the search, the best permutation found is returned and `get_gap` tells how far it may
+/// still be from the optimum.
+pub struct BranchAndBoundSolver<'a, C: Cost = usize> {
+    instance: &'a Instance<C>,
+    max_time: u128,
+    monitor: Monitor,
+    nodes_explored: usize,
+    lower_bound: C::Wide,
+    upper_bound: C::Wide,
+}
+
+struct Node<W> {
+    /// Locations of the first `locations.len()` facilities of the branching order.
+    locations: Vec<usize>,
+    bound: W,
+}
+
+impl<'a, C: Cost> BranchAndBoundSolver<'a, C> {
+    pub fn new(instance: &Instance<C>, max_time: u128) -> BranchAndBoundSolver<'_, C> {
+        BranchAndBoundSolver {
+            instance,
+            max_time,
+            monitor: Monitor::default(),
+            nodes_explored: 0,
+            lower_bound: C::Wide::default(),
+            upper_bound: C::Wide::default(),
+        }
+    }
+
+    /// Number of search tree nodes whose bound was computed during the last run.
+    pub fn get_nodes_explored(&self) -> usize {
+        self.nodes_explored
+    }
+
+    /// Proven lower bound on the optimal cost after the last run.
+    pub fn get_lower_bound(&self) -> C::Wide {
+        self.lower_bound
+    }
+
+    /// Difference between the returned cost and the proven lower bound; zero when the
+    /// returned permutation is optimal.
+    pub fn get_gap(&self) -> C::Wide {
+        self.upper_bound - self.lower_bound
+    }
+
+    /// Facilities with the largest total flow are branched on first, which tightens the
+    /// bounds near the root.
+    fn branching_order(&self) -> Vec<usize> {
+        let a = &self.instance.matrix_a;
+        let n = self.instance.size;
+        let mut order: Vec<usize> = (0..n).collect();
+        let flows: Vec<C::Wide> = (0..n).map(|i| (0..n).map(|j| a[i][j].widen() + a[j][i].widen()).sum()).collect();
+        order.sort_by(|&i, &j| cmp_costs(&flows[j], &flows[i]));
+        order
+    }
+}
+
+impl<'a, C: Cost> Solver<C> for BranchAndBoundSolver<'a, C> {
+    fn solve(
+        &mut self,
+        initial_solution: Vec<usize>,
+        _rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        let n = self.instance.size;
+        let order = self.branching_order();
+        let start = std::time::Instant::now();
+        let mut tracer = Tracer::new(&self.monitor);
+
+        let mut best_solution = initial_solution;
+        let mut best_cost = self.instance.evaluate(&best_solution)?.widen();
+        tracer.record(0, C::wide_to_f64(best_cost), C::wide_to_f64(best_cost));
+        let mut solution_changes = 0;
+        self.nodes_explored = 1;
+
+        let mut stack = vec![Node {
+            locations: Vec::with_capacity(n),
+            bound: bounds::gilmore_lawler_partial(self.instance, &order, &[]),
+        }];
+        let mut interrupted = false;
+
+        while let Some(node) = stack.pop() {
+            if node.bound >= best_cost {
+                continue;
+            }
+            if start.elapsed().as_nanos() > self.max_time || tracer.is_cancelled() {
+                stack.push(node);
+                interrupted = true;
+                break;
+            }
+
+            if node.locations.len() == n {
+                best_cost = node.bound;
+                best_solution = vec![0; n];
+                order.iter().zip(node.locations.iter()).for_each(|(&i, &k)| best_solution[i] = k);
+                solution_changes += 1;
+                tracer.record(self.nodes_explored, C::wide_to_f64(best_cost), C::wide_to_f64(best_cost));
+                continue;
+            }
+
+            let facility = order[node.locations.len()];
+            let mut children: Vec<Node<C::Wide>> = (0..n)
+                .filter(|&k| !node.locations.contains(&k) && self.instance.get_constraints().allows(facility, k))
+                .map(|k| {
+                    let mut locations = node.locations.clone();
+                    locations.push(k);
+                    let bound = bounds::gilmore_lawler_partial(self.instance, &order, &locations);
+                    Node { locations, bound }
+                })
+                .collect();
+            self.nodes_explored += children.len();
+            children.retain(|child| child.bound < best_cost);
+
+            // The most promising child ends up on top of the stack.
+            children.sort_by(|first, second| cmp_costs(&second.bound, &first.bound));
+            stack.extend(children);
+        }
+
+        self.lower_bound = match stack.iter().map(|node| node.bound).min_by(cmp_costs) {
+            Some(bound) if interrupted && bound < best_cost => bound,
+            _ => best_cost,
+        };
+        self.upper_bound = best_cost;
+
+        Ok(Solution {
+            permutation: best_solution,
+            evaluations: self.nodes_explored,
+            solution_changes,
+            trace: tracer.finish(self.nodes_explored, C::wide_to_f64(best_cost), C::wide_to_f64(best_cost)),
+        })
+    }
+
+    fn get_name(&self) -> String {
+        "BranchAndBoundSolver".to_string()
+    }
+
+    fn get_instance(&self) -> &Instance<C> {
+        self.instance
+    }
+
+    fn set_time_limit(&mut self, time_limit: u128) {
+        self.max_time = time_limit;
+    }
+
+    fn get_time_limit(&self) -> u128 {
+        self.max_time
+    }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::{constraints::Constraints, get_random_permutation, matrix::LawlerCosts};
+
+    fn brute_force<C: Cost>(instance: &Instance<C>, perm: &mut Vec<usize>, depth: usize) -> C {
+        if depth == perm.len() {
+            return instance.evaluate(perm).unwrap();
+        }
+        (depth..perm.len())
+            .map(|i| {
+                perm.swap(depth, i);
+                let best = brute_force(instance, perm, depth + 1);
+                perm.swap(depth, i);
+                best
+            })
+            .min_by(cmp_costs)
+            .unwrap()
+    }
+
+    #[test]
+    fn finds_optimum_of_small_instances() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for n in 2..=7 {
+            let mut random_matrix = || -> Vec<Vec<usize>> {
+                (0..n).map(|_| (0..n).map(|_| rng.gen_range(0..20)).collect()).collect()
+            };
+            let instance = Instance::new(random_matrix(), random_matrix(), 0, vec![]);
+            let mut solver = BranchAndBoundSolver::new(&instance, u128::MAX);
+
+            let start = get_random_permutation(n, &mut rng);
+            let solution = solver.solve(start, &mut rng).unwrap();
+
+            let optimum = brute_force(&instance, &mut (0..n).collect(), 0);
+            assert_eq!(optimum, instance.evaluate(&solution.permutation).unwrap());
+            assert_eq!(0, solver.get_gap());
+        }
+    }
+
+    #[test]
+    fn finds_optimum_of_small_lawler_instances() {
+        let mut rng = StdRng::seed_from_u64(12);
+        for n in 2..=5 {
+            let costs = LawlerCosts::from_flat(n, (0..n.pow(4)).map(|_| rng.gen_range(0..20)).collect());
+            let instance: Instance = Instance::from_lawler_costs(costs, 0, vec![]);
+            let mut solver = BranchAndBoundSolver::new(&instance, u128::MAX);
+
+            let start = get_random_permutation(n, &mut rng);
+            let solution = solver.solve(start, &mut rng).unwrap();
+
+            let optimum = brute_force(&instance, &mut (0..n).collect(), 0);
+            assert_eq!(optimum, instance.evaluate(&solution.permutation).unwrap());
+            assert_eq!(0, solver.get_gap());
+        }
+    }
+
+    #[test]
+    fn finds_optimum_of_signed_and_real_instances() {
+        let mut rng = StdRng::seed_from_u64(13);
+        for n in 2..=6 {
+            let mut signed_matrix =
+                || -> Vec<Vec<i64>> { (0..n).map(|_| (0..n).map(|_| rng.gen_range(-20..20)).collect()).collect() };
+            let signed = Instance::new(signed_matrix(), signed_matrix(), 0, vec![]);
+            let start = get_random_permutation(n, &mut rng);
+            let mut solver = BranchAndBoundSolver::new(&signed, u128::MAX);
+            let solution = solver.solve(start.clone(), &mut rng).unwrap();
+            let optimum = brute_force(&signed, &mut (0..n).collect(), 0);
+            assert_eq!(optimum, signed.evaluate(&solution.permutation).unwrap());
+            assert_eq!(0, solver.get_gap());
+
+            let halve = |matrix: &crate::matrix::Matrix<i64>| -> Vec<Vec<f64>> {
+                matrix.rows().map(|row| row.iter().map(|&x| x as f64 / 2.0).collect()).collect()
+            };
+            let real = Instance::new(halve(&signed.matrix_a), halve(&signed.matrix_b), 0.0, vec![]);
+            let mut solver = BranchAndBoundSolver::new(&real, u128::MAX);
+            let solution = solver.solve(start, &mut rng).unwrap();
+            assert_eq!(optimum as f64 / 4.0, real.evaluate(&solution.permutation).unwrap());
+            assert_eq!(0.0, solver.get_gap());
+        }
+    }
+
+    #[test]
+    fn counts_only_the_children_the_constraints_allow() {
+        let n: usize = 5;
+        let adjacent = || -> Vec<Vec<usize>> {
+            (0..n).map(|i| (0..n).map(|j| if i.abs_diff(j) == 1 { 10 } else { 1 }).collect()).collect()
+        };
+        let mut instance = Instance::new(adjacent(), adjacent(), 0, vec![]);
+        let mut constraints = Constraints::new(n);
+        (0..n).for_each(|i| constraints.fix(i, i));
+        instance.set_constraints(constraints).unwrap();
+
+        let mut solver = BranchAndBoundSolver::new(&instance, u128::MAX);
+        let solution = solver.solve((0..n).collect(), &mut StdRng::seed_from_u64(0)).unwrap();
+        assert_eq!((0..n).collect::<Vec<_>>(), solution.permutation);
+        // One allowed child per facility at most, next to the root.
+        assert!((2..=n + 1).contains(&solver.get_nodes_explored()));
+        assert_eq!(solution.evaluations, solver.get_nodes_explored());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{
+    cost::Cost,
+    instance::{Formulation, Instance},
+};
+
+use super::eval_diff_reassign;
+
+/// Cost differences of every pairwise swap of the current permutation, kept up to date
+/// after each move as in Taillard's robust tabu search.
+///
+/// Entries follow the `Neighbourhood::delta` convention: `cost(perm) - cost(perm after
+/// swap)`, so a positive value is an improving move. Building the matrix takes O(n^3); after
+/// a swap of `r` and `s` every entry not involving `r` or `s` is updated in O(1) and the
+/// remaining 2n entries are recomputed in O(n), which makes a full neighbourhood scan O(n^2).
+/// Lawler instances have no such update and recompute every entry in O(n^3).
+pub struct DeltaMatrix<'a, C: Cost = usize> {
+    instance: &'a Instance<C>,
+    deltas: Vec<Vec<C::Wide>>,
+}
+
+impl<'a, C: Cost> DeltaMatrix<'a, C> {
+    pub fn new(instance: &'a Instance<C>, perm: &[usize]) -> DeltaMatrix<'a, C> {
+        let n = perm.len();
+        let deltas = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| if i < j { swap_delta(instance, perm, i, j) } else { C::Wide::default() })
+                    .collect()
+            })
+            .collect();
+        DeltaMatrix { instance, deltas }
+    }
+
+    /// Cost decrease obtained by swapping positions `i` and `j` of the current permutation.
+    pub fn get(&self, i: usize, j: usize) -> C::Wide {
+        if i < j {
+            self.deltas[i][j]
+        } else {
+            self.deltas[j][i]
+        }
+    }
+
+    /// Swaps positions `r` and `s` of `perm` and updates all deltas to the new permutation.
+    pub fn apply_swap(&mut self, perm: &mut [usize], r: usize, s: usize) {
+        let n = perm.len();
+        perm.swap(r, s);
+        if let Formulation::Lawler(_) = self.instance.get_formulation() {
+            *self = DeltaMatrix::new(self.instance, perm);
+            return;
+        }
+
+        // The linear term of a swap depends only on the swapped facilities, so the update
+        // below holds with it as well.
+        let a = &self.instance.matrix_a;
+        let b = &self.instance.matrix_b;
+        let symmetric = self.instance.is_symmetric();
+        let (pr, ps) = (perm[r], perm[s]);
+        for i in 0..n {
+            for j in i + 1..n {
+                if i == r || i == s || j == r || j == s {
+                    self.deltas[i][j] = swap_delta(self.instance, perm, i, j);
+                    continue;
+                }
+                let (pi, pj) = (perm[i], perm[j]);
+                let first = (v(a[r][i]) - v(a[r][j]) + v(a[s][j]) - v(a[s][i]))
+                    * (v(b[ps][pi]) - v(b[ps][pj]) + v(b[pr][pj]) - v(b[pr][pi]));
+                let second = if symmetric {
+                    first
+                } else {
+                    (v(a[i][r]) - v(a[j][r]) + v(a[j][s]) - v(a[i][s]))
+                        * (v(b[pi][ps]) - v(b[pj][ps]) + v(b[pj][pr]) - v(b[pi][pr]))
+                };
+                self.deltas[i][j] -= first + second;
+            }
+        }
+    }
+}
+
+/// Cost decrease of swapping positions `i` and `j` of `perm`, computed from scratch in O(n).
+pub fn swap_delta<C: Cost>(instance: &Instance<C>, perm: &[usize], i: usize, j: usize) -> C::Wide {
+    if i.min(j) >= instance.get_facility_count() {
+        // Two dummy facilities of a rectangular instance.
+        return C::Wide::default();
+    }
+    let (pi, pj) = (perm[i], perm[j]);
+    match instance.get_formulation() {
+        Formulation::KoopmansBeckmann => quadratic_swap_delta(instance, perm, i, j),
+        Formulation::Linear(costs) => {
+            quadratic_swap_delta(instance, perm, i, j) + v(costs[i][pi]) + v(costs[j][pj])
+                - v(costs[i][pj])
+                - v(costs[j][pi])
+        }
+        Formulation::Lawler(_) => eval_diff_reassign(instance, perm, &[i, j], &[pj, pi]),
+    }
+}
+
+/// Swap delta of the Koopmans–Beckmann term. Symmetric instances need only one of the two
+/// sums over the other facilities.
+fn quadratic_swap_delta<C: Cost>(instance: &Instance<C>, perm: &[usize], i: usize, j: usize) -> C::Wide {
+    let a = &instance.matrix_a;
+    let b = &instance.matrix_b;
+    let (pi, pj) = (perm[i], perm[j]);
+    let (row_ai, row_aj, row_bi, row_bj) = (a.row(i), a.row(j), b.row(pi), b.row(pj));
+
+    let mut delta = (v(row_ai[i]) - v(row_aj[j])) * (v(row_bj[pj]) - v(row_bi[pi]));
+    if instance.is_symmetric() {
+        let mut half = C::Wide::default();
+        for (k, &pk) in instance.assignment(perm).iter().enumerate() {
+            if k != i && k != j {
+                half += (v(row_ai[k]) - v(row_aj[k])) * (v(row_bj[pk]) - v(row_bi[pk]));
+            }
+        }
+        return -(delta + half + half);
+    }
+
+    let (column_ai, column_aj, column_bi, column_bj) = (a.column(i), a.column(j), b.column(pi), b.column(pj));
+    delta += (v(row_ai[j]) - v(row_aj[i])) * (v(row_bj[pi]) - v(row_bi[pj]));
+    for (k, &pk) in instance.assignment(perm).iter().enumerate() {
+        if k != i && k != j {
+            delta += (v(column_ai[k]) - v(column_aj[k])) * (v(column_bj[pk]) - v(column_bi[pk]))
+                + (v(row_ai[k]) - v(row_aj[k])) * (v(row_bj[pk]) - v(row_bi[pk]));
+        }
+    }
+    -delta
+}
+
+fn v<C: Cost>(value: C) -> C::Wide {
+    value.widen()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::get_random_permutation;
+
+    fn random_instance(n: usize, rng: &mut StdRng) -> Instance {
+        let mut random_matrix = || {
+            (0..n)
+                .map(|_| (0..n).map(|_| rng.gen_range(0..10)).collect())
+                .collect()
+        };
+        let matrix_a = random_matrix();
+        let matrix_b = random_matrix();
+        Instance::new(matrix_a, matrix_b, 0, vec![])
+    }
+
+    #[test]
+    fn deltas_stay_exact_after_swaps() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let instance = random_instance(9, &mut rng);
+        let mut perm = get_random_permutation(9, &mut rng);
+        let mut deltas = DeltaMatrix::new(&instance, &perm);
+
+        for _ in 0..50 {
+            let (r, s) = crate::get_random_pair(9, &mut rng);
+            let before = instance.evaluate_wide(&perm);
+            let expected = deltas.get(r, s);
+            deltas.apply_swap(&mut perm, r, s);
+            assert_eq!(before - instance.evaluate_wide(&perm), expected);
+
+            for i in 0..9 {
+                for j in i + 1..9 {
+                    assert_eq!(deltas.get(i, j), swap_delta(&instance, &perm, i, j));
+                }
+            }
+        }
+    }
+}
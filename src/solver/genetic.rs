@@ -0,0 +1,389 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+use crate::{
+    cost::{cmp_costs, Cost},
+    instance::Instance,
+};
+
+use super::{
+    local_search::LocalSearch,
+    neighbourhood::{Neighbourhood, Swap},
+    monitor::{CancellationToken, Monitor},
+    trace::Tracer,
+    Solution, Solver, SolvingError,
+};
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Crossover {
+    Cycle,
+    PartiallyMapped,
+    Order,
+    /// Drezner's cohesive crossover: facilities placed close to a random pivot location in
+    /// the first parent keep their places, the others come from the second parent.
+    Cohesive,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Selection {
+    Tournament { size: usize },
+    Uniform,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Replacement {
+    /// The offspring replace the whole population, except for the best individual.
+    Generational,
+    /// Every offspring replaces the worst individual if it is better and not a duplicate.
+    SteadyState,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeneticConfig {
+    pub population_size: usize,
+    pub crossover: Crossover,
+    pub selection: Selection,
+    pub replacement: Replacement,
+    /// Local search applied to every offspring; setting it makes the algorithm memetic.
+    pub offspring_search: Option<LocalSearch>,
+    /// Probability of applying a random swap to an offspring.
+    pub mutation_rate: f64,
+    pub max_generations: usize,
+}
+
+impl Default for GeneticConfig {
+    fn default() -> Self {
+        GeneticConfig {
+            population_size: 50,
+            crossover: Crossover::Cohesive,
+            selection: Selection::Tournament { size: 3 },
+            replacement: Replacement::SteadyState,
+            offspring_search: None,
+            mutation_rate: 0.2,
+            max_generations: 1000,
+        }
+    }
+}
+
+struct Individual<C: Cost> {
+    permutation: Vec<usize>,
+    cost: C,
+}
+
+pub struct GeneticSolver<'a, C: Cost = usize> {
+    instance: &'a Instance<C>,
+    max_time: u128,
+    monitor: Monitor,
+    config: GeneticConfig,
+}
+
+impl<'a, C: Cost> GeneticSolver<'a, C> {
+    pub fn new(instance: &Instance<C>, max_time: u128, config: GeneticConfig) -> GeneticSolver<'_, C> {
+        GeneticSolver {
+            instance,
+            max_time,
+            monitor: Monitor::default(),
+            config,
+        }
+    }
+
+    fn select<'p>(&self, population: &'p [Individual<C>], rng: &mut StdRng) -> &'p Individual<C> {
+        match self.config.selection {
+            Selection::Uniform => &population[rng.gen_range(0..population.len())],
+            Selection::Tournament { size } => (0..size.max(1))
+                .map(|_| &population[rng.gen_range(0..population.len())])
+                .min_by(|first, second| cmp_costs(&first.cost, &second.cost))
+                .expect("Tournament should not be empty"),
+        }
+    }
+
+    fn crossover(&self, first: &[usize], second: &[usize], rng: &mut StdRng) -> Vec<usize> {
+        match self.config.crossover {
+            Crossover::Cycle => cycle_crossover(first, second, rng),
+            Crossover::PartiallyMapped => partially_mapped_crossover(first, second, rng),
+            Crossover::Order => order_crossover(first, second, rng),
+            Crossover::Cohesive => cohesive_crossover(self.instance, first, second, rng),
+        }
+    }
+
+    /// Returns the improved offspring and the number of neighbours evaluated.
+    fn improve(
+        &self,
+        offspring: Vec<usize>,
+        time_left: u128,
+        cancellation: Option<&CancellationToken>,
+        rng: &mut StdRng,
+    ) -> (Vec<usize>, usize) {
+        match self.config.offspring_search {
+            None => (offspring, 0),
+            Some(local_search) => {
+                let solution = local_search.run(self.instance, offspring, time_left, cancellation, rng);
+                (solution.permutation, solution.evaluations)
+            }
+        }
+    }
+}
+
+impl<'a, C: Cost> Solver<C> for GeneticSolver<'a, C> {
+    fn solve(
+        &mut self,
+        initial_solution: Vec<usize>,
+        rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        self.instance.get_constraints().check(&initial_solution)?;
+        let n = self.instance.size;
+        let start = std::time::Instant::now();
+        let time_left = |start: &std::time::Instant| self.max_time.saturating_sub(start.elapsed().as_nanos());
+        let mut evaluations = 0;
+        let mut solution_changes = 0;
+        let mut tracer = Tracer::new(&self.monitor);
+
+        let mut population: Vec<Individual<C>> = Vec::with_capacity(self.config.population_size);
+        let mut permutation = initial_solution;
+        while population.len() < self.config.population_size.max(2) {
+            let (permutation_improved, search_evaluations) = self.improve(permutation, time_left(&start), tracer.get_cancellation(), rng);
+            evaluations += search_evaluations + 1;
+            population.push(Individual {
+                cost: self.instance.evaluate(&permutation_improved)?,
+                permutation: permutation_improved,
+            });
+            permutation = self.instance.random_permutation(rng);
+        }
+        let mut best = population.iter().map(|individual| individual.cost).min_by(cmp_costs).unwrap();
+        tracer.record(evaluations, best.to_f64(), best.to_f64());
+
+        let mut generation = 0;
+        while generation < self.config.max_generations && start.elapsed().as_nanos() < self.max_time && !tracer.is_cancelled() {
+            let mut offspring_population = Vec::with_capacity(population.len());
+            for _ in 0..population.len() {
+                if start.elapsed().as_nanos() >= self.max_time || tracer.is_cancelled() {
+                    break;
+                }
+                let first = self.select(&population, rng);
+                let second = self.select(&population, rng);
+                let mut offspring = self.crossover(&first.permutation, &second.permutation, rng);
+                self.instance.repair(&mut offspring);
+                if n > 1 && rng.gen::<f64>() < self.config.mutation_rate {
+                    if let Some(mv) = Swap.random_feasible_move(self.instance, &offspring, rng) {
+                        Swap.apply(&mut offspring, mv);
+                    }
+                }
+                let (offspring, search_evaluations) = self.improve(offspring, time_left(&start), tracer.get_cancellation(), rng);
+                evaluations += search_evaluations + 1;
+                let cost = self.instance.evaluate(&offspring)?;
+                if cost < best {
+                    best = cost;
+                    solution_changes += 1;
+                }
+                tracer.record(evaluations, cost.to_f64(), best.to_f64());
+                let offspring = Individual { permutation: offspring, cost };
+
+                match self.config.replacement {
+                    Replacement::Generational => offspring_population.push(offspring),
+                    Replacement::SteadyState => replace_worst(&mut population, offspring),
+                }
+            }
+
+            if let Replacement::Generational = self.config.replacement {
+                // The best individuals of the old population fill the places left by the
+                // offspring, which always keeps at least the elite one.
+                let size = population.len();
+                offspring_population.sort_by(|first, second| cmp_costs(&first.cost, &second.cost));
+                offspring_population.truncate(size - 1);
+                population.sort_by(|first, second| cmp_costs(&first.cost, &second.cost));
+                let missing = size - offspring_population.len();
+                offspring_population.extend(population.drain(..missing));
+                population = offspring_population;
+            }
+            generation += 1;
+        }
+
+        let best = population
+            .into_iter()
+            .min_by(|first, second| cmp_costs(&first.cost, &second.cost))
+            .unwrap();
+        Ok(Solution {
+            trace: tracer.finish(evaluations, best.cost.to_f64(), best.cost.to_f64()),
+            permutation: best.permutation,
+            evaluations,
+            solution_changes,
+        })
+    }
+
+    fn get_name(&self) -> String {
+        "GeneticSolver".to_string()
+    }
+
+    fn get_instance(&self) -> &Instance<C> {
+        self.instance
+    }
+
+    fn set_time_limit(&mut self, time_limit: u128) {
+        self.max_time = time_limit;
+    }
+
+    fn get_time_limit(&self) -> u128 {
+        self.max_time
+    }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
+}
+
+fn replace_worst<C: Cost>(population: &mut [Individual<C>], offspring: Individual<C>) {
+    let duplicate = population.iter().any(|individual| individual.permutation == offspring.permutation);
+    let (worst_idx, worst) = population
+        .iter()
+        .enumerate()
+        .max_by(|(_, first), (_, second)| cmp_costs(&first.cost, &second.cost))
+        .expect("Population should not be empty");
+    if !duplicate && offspring.cost < worst.cost {
+        population[worst_idx] = offspring;
+    }
+}
+
+/// Every position keeps the location of one of the parents: positions are split into the
+/// cycles of the mapping between the parents, taken alternately from each of them.
+pub fn cycle_crossover<R: Rng + ?Sized>(first: &[usize], second: &[usize], rng: &mut R) -> Vec<usize> {
+    let n = first.len();
+    let mut position_in_first = vec![0; n];
+    first.iter().enumerate().for_each(|(i, &location)| position_in_first[location] = i);
+
+    let mut offspring = vec![usize::MAX; n];
+    let mut take_first = rng.gen::<bool>();
+    for start in 0..n {
+        if offspring[start] != usize::MAX {
+            continue;
+        }
+        let mut i = start;
+        while offspring[i] == usize::MAX {
+            offspring[i] = if take_first { first[i] } else { second[i] };
+            i = position_in_first[second[i]];
+        }
+        take_first = !take_first;
+    }
+    offspring
+}
+
+/// PMX: a random segment is copied from the first parent, the other positions take the
+/// second parent's locations, following the segment's mapping on conflicts.
+pub fn partially_mapped_crossover<R: Rng + ?Sized>(first: &[usize], second: &[usize], rng: &mut R) -> Vec<usize> {
+    let n = first.len();
+    let (from, to) = random_segment(n, rng);
+    let mut offspring = second.to_vec();
+    let mut position_in_offspring = vec![0; n];
+    offspring.iter().enumerate().for_each(|(i, &location)| position_in_offspring[location] = i);
+
+    for i in from..to {
+        let j = position_in_offspring[first[i]];
+        offspring.swap(i, j);
+        position_in_offspring[offspring[i]] = i;
+        position_in_offspring[offspring[j]] = j;
+    }
+    offspring
+}
+
+/// OX: a random segment is copied from the first parent, the remaining positions are
+/// filled with the missing locations in the order they appear in the second parent.
+pub fn order_crossover<R: Rng + ?Sized>(first: &[usize], second: &[usize], rng: &mut R) -> Vec<usize> {
+    let n = first.len();
+    let (from, to) = random_segment(n, rng);
+    let mut used = vec![false; n];
+    let mut offspring = vec![usize::MAX; n];
+    for i in from..to {
+        offspring[i] = first[i];
+        used[first[i]] = true;
+    }
+
+    let mut remaining = (0..n).map(|k| second[(to + k) % n]).filter(|&location| !used[location]);
+    for k in 0..n - (to - from) {
+        offspring[(to + k) % n] = remaining.next().expect("Parents should be permutations");
+    }
+    offspring
+}
+
+/// Drezner's cohesive crossover. Facilities whose location in the first parent is at most
+/// the median distance from a random pivot location keep it; the others take their
+/// location from the second parent when it is still free, and a random free one otherwise.
+pub fn cohesive_crossover<C: Cost, R: Rng + ?Sized>(
+    instance: &Instance<C>,
+    first: &[usize],
+    second: &[usize],
+    rng: &mut R,
+) -> Vec<usize> {
+    let n = first.len();
+    let pivot = rng.gen_range(0..n);
+    let distances = &instance.matrix_b[pivot];
+    let mut sorted_distances = distances.to_vec();
+    sorted_distances.sort_unstable_by(cmp_costs);
+    let median = sorted_distances[(n - 1) / 2];
+
+    let mut offspring = vec![usize::MAX; n];
+    let mut used = vec![false; n];
+    for i in 0..n {
+        if distances[first[i]] <= median {
+            offspring[i] = first[i];
+            used[first[i]] = true;
+        }
+    }
+    for i in 0..n {
+        if offspring[i] == usize::MAX && !used[second[i]] {
+            offspring[i] = second[i];
+            used[second[i]] = true;
+        }
+    }
+
+    let mut free: Vec<usize> = (0..n).filter(|&location| !used[location]).collect();
+    free.shuffle(rng);
+    offspring
+        .iter_mut()
+        .filter(|location| **location == usize::MAX)
+        .zip(free)
+        .for_each(|(location, free_location)| *location = free_location);
+    offspring
+}
+
+fn random_segment<R: Rng + ?Sized>(n: usize, rng: &mut R) -> (usize, usize) {
+    let (x, y) = (rng.gen_range(0..=n), rng.gen_range(0..=n));
+    (x.min(y), x.max(y))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::get_random_permutation;
+
+    #[test]
+    fn crossovers_produce_permutations() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let n = 12;
+        let matrix: Vec<Vec<usize>> = (0..n).map(|i: usize| (0..n).map(|j| i.abs_diff(j)).collect()).collect();
+        let instance = Instance::new(matrix.clone(), matrix, 0, vec![]);
+
+        for _ in 0..200 {
+            let first = get_random_permutation(n, &mut rng);
+            let second = get_random_permutation(n, &mut rng);
+            let cycle_offspring = cycle_crossover(&first, &second, &mut rng);
+            assert!((0..n).all(|i| cycle_offspring[i] == first[i] || cycle_offspring[i] == second[i]));
+
+            let offspring = [
+                cycle_offspring,
+                partially_mapped_crossover(&first, &second, &mut rng),
+                order_crossover(&first, &second, &mut rng),
+                cohesive_crossover(&instance, &first, &second, &mut rng),
+            ];
+            for child in offspring {
+                let mut sorted = child.clone();
+                sorted.sort_unstable();
+                assert_eq!((0..n).collect::<Vec<_>>(), sorted);
+            }
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use rand::{rngs::StdRng, Rng};
+use serde::Deserialize;
+
+use crate::{
+    cost::{cmp_costs, Cost},
+    instance::{EvaluationError, Instance},
+};
+
+use super::{
+    local_search::LocalSearch,
+    monitor::Monitor,
+    trace::Tracer,
+    Solution, Solver, SolvingError,
+};
+
+/// A pair of facilities placed on a pair of locations by the first construction stage.
+type PairPlacement = ((usize, usize), (usize, usize));
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraspConfig {
+    /// Fraction of the cheapest candidates forming the restricted candidate list.
+    pub alpha: f64,
+    /// Fraction of the facility and location pairs considered by the first stage.
+    pub beta: f64,
+    pub local_search: LocalSearch,
+    /// Number of elite solutions kept for path relinking; zero disables relinking.
+    pub elite_size: usize,
+}
+
+impl Default for GraspConfig {
+    fn default() -> Self {
+        GraspConfig {
+            alpha: 0.5,
+            beta: 0.1,
+            local_search: LocalSearch::Greedy,
+            elite_size: 0,
+        }
+    }
+}
+
+/// Statistics of the randomised constructions of the last run.
+#[derive(Debug, Default)]
+pub struct ConstructionStats<C: Cost = usize> {
+    pub constructions: usize,
+    pub mean_constructed_cost: f64,
+    /// `None` when nothing was constructed.
+    pub best_constructed_cost: Option<C>,
+    /// Mean cost decrease obtained by the local search after a construction.
+    pub mean_improvement: f64,
+}
+
+/// GRASP of Li, Pardalos and Resende. The first stage places two facilities with a large
+/// flow on two close locations; the second stage adds the remaining facilities one at a
+/// time, choosing at random among the cheapest placements. Every constructed solution is
+/// improved by local search and, optionally, relinked with a pool of elite solutions.
+pub struct GraspSolver<'a, C: Cost = usize> {
+    instance: &'a Instance<C>,
+    max_time: u128,
+    monitor: Monitor,
+    config: GraspConfig,
+    stats: ConstructionStats<C>,
+}
+
+impl<'a, C: Cost> GraspSolver<'a, C> {
+    pub fn new(instance: &Instance<C>, max_time: u128, config: GraspConfig) -> GraspSolver<'_, C> {
+        GraspSolver {
+            instance,
+            max_time,
+            monitor: Monitor::default(),
+            config,
+            stats: ConstructionStats::default(),
+        }
+    }
+
+    pub fn get_construction_stats(&self) -> &ConstructionStats<C> {
+        &self.stats
+    }
+
+    fn rcl_size(&self, candidates: usize) -> usize {
+        ((self.config.alpha * candidates as f64).floor() as usize).clamp(1, candidates.max(1))
+    }
+
+    /// Candidates of the first stage: the largest flows paired with the smallest distances,
+    /// sorted by their product.
+    fn first_stage_candidates(&self) -> Vec<PairPlacement> {
+        let a = &self.instance.matrix_a;
+        let b = &self.instance.matrix_b;
+        let n = self.instance.size;
+        let pairs: Vec<(usize, usize)> = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j))).collect();
+        let considered = ((self.config.beta * pairs.len() as f64).floor() as usize).clamp(1, pairs.len().max(1));
+
+        let mut flows = pairs.clone();
+        let flow = |(i, j): (usize, usize)| a[i][j].widen() + a[j][i].widen();
+        let distance = |(k, l): (usize, usize)| b[k][l].widen() + b[l][k].widen();
+        flows.sort_by(|&first, &second| cmp_costs(&flow(second), &flow(first)));
+        let mut distances = pairs;
+        distances.sort_by(|&first, &second| cmp_costs(&distance(first), &distance(second)));
+
+        let constraints = self.instance.get_constraints();
+        let allowed = |&((i, j), (k, l)): &PairPlacement| {
+            constraints.allows(i, k) && constraints.allows(j, l) || constraints.allows(i, l) && constraints.allows(j, k)
+        };
+        let mut candidates: Vec<PairPlacement> = flows
+            .into_iter()
+            .zip(distances)
+            .take(considered)
+            .filter(allowed)
+            .collect();
+        let product = |&(facilities, locations): &PairPlacement| flow(facilities) * distance(locations);
+        candidates.sort_by(|first, second| cmp_costs(&product(first), &product(second)));
+        candidates
+    }
+
+    fn construct(&self, first_stage: &[PairPlacement], rng: &mut StdRng) -> Vec<usize> {
+        let n = self.instance.size;
+        let constraints = self.instance.get_constraints();
+        let mut partial = PartialAssignment::new(n);
+
+        if !first_stage.is_empty() {
+            let ((i, j), (k, l)) = first_stage[rng.gen_range(0..self.rcl_size(first_stage.len()))];
+            let drawn = rng.gen::<bool>();
+            let direct = constraints.allows(i, k) && constraints.allows(j, l);
+            let reversed = constraints.allows(i, l) && constraints.allows(j, k);
+            let (k, l) = if !reversed || (direct && drawn) { (k, l) } else { (l, k) };
+            partial.assign(self.instance, i, k);
+            partial.assign(self.instance, j, l);
+        }
+
+        while partial.assigned < n {
+            let mut candidates: Vec<(C::Wide, usize, usize)> = (0..n)
+                .filter(|&i| partial.perm[i] == usize::MAX)
+                .flat_map(|i| (0..n).map(move |k| (i, k)))
+                .filter(|&(i, k)| !partial.location_used[k] && constraints.allows(i, k))
+                .map(|(i, k)| (partial.costs[i][k], i, k))
+                .collect();
+            if candidates.is_empty() {
+                // Dead end: the remaining facilities take the free locations in order and the
+                // repair below moves them to allowed ones.
+                let free_locations = (0..n).filter(|&k| !partial.location_used[k]).collect::<Vec<usize>>();
+                let unassigned = partial.perm.iter_mut().filter(|location| **location == usize::MAX);
+                unassigned.zip(free_locations).for_each(|(location, k)| *location = k);
+                break;
+            }
+            let rcl_size = self.rcl_size(candidates.len());
+            candidates.select_nth_unstable_by(rcl_size - 1, |first, second| cmp_costs(&first.0, &second.0));
+            let (_, i, k) = candidates[rng.gen_range(0..rcl_size)];
+            partial.assign(self.instance, i, k);
+        }
+        self.instance.repair(&mut partial.perm);
+        partial.perm
+    }
+
+    /// Walks from `from` towards `guide` by swaps fixing one position at a time and returns
+    /// the best feasible permutation on the path.
+    fn relink(&self, from: &[usize], guide: &[usize]) -> Result<(Vec<usize>, C), EvaluationError> {
+        let mut current = from.to_vec();
+        let mut best = current.clone();
+        let mut best_cost = self.instance.evaluate(&current)?;
+        let mut position_of = vec![0; current.len()];
+        current.iter().enumerate().for_each(|(i, &k)| position_of[k] = i);
+
+        for i in 0..current.len() {
+            if current[i] == guide[i] {
+                continue;
+            }
+            let j = position_of[guide[i]];
+            current.swap(i, j);
+            position_of[current[i]] = i;
+            position_of[current[j]] = j;
+            let Ok(cost) = self.instance.evaluate(&current) else {
+                continue;
+            };
+            if cost < best_cost {
+                best_cost = cost;
+                best.clone_from(&current);
+            }
+        }
+        Ok((best, best_cost))
+    }
+}
+
+struct PartialAssignment<C: Cost> {
+    perm: Vec<usize>,
+    location_used: Vec<bool>,
+    assigned: usize,
+    /// costs[i][k]: interaction cost with the assigned facilities of placing i at k.
+    costs: Vec<Vec<C::Wide>>,
+}
+
+impl<C: Cost> PartialAssignment<C> {
+    fn new(n: usize) -> PartialAssignment<C> {
+        PartialAssignment {
+            perm: vec![usize::MAX; n],
+            location_used: vec![false; n],
+            assigned: 0,
+            costs: vec![vec![C::Wide::default(); n]; n],
+        }
+    }
+
+    fn assign(&mut self, instance: &Instance<C>, facility: usize, location: usize) {
+        let a = &instance.matrix_a;
+        let b = &instance.matrix_b;
+        self.perm[facility] = location;
+        self.location_used[location] = true;
+        self.assigned += 1;
+        for (i, row) in self.costs.iter_mut().enumerate() {
+            for (k, cost) in row.iter_mut().enumerate() {
+                *cost += a[i][facility].widen() * b[k][location].widen()
+                    + a[facility][i].widen() * b[location][k].widen();
+            }
+        }
+    }
+}
+
+impl<'a, C: Cost> Solver<C> for GraspSolver<'a, C> {
+    fn solve(
+        &mut self,
+        initial_solution: Vec<usize>,
+        rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        let start = std::time::Instant::now();
+        let time_left = |start: &std::time::Instant| self.max_time.saturating_sub(start.elapsed().as_nanos());
+        let first_stage = self.first_stage_candidates();
+
+        let mut best_cost = self.instance.evaluate(&initial_solution)?;
+        let mut best = initial_solution;
+        let mut tracer = Tracer::new(&self.monitor);
+        tracer.record(0, best_cost.to_f64(), best_cost.to_f64());
+        let mut elite: Vec<(Vec<usize>, C)> = Vec::with_capacity(self.config.elite_size);
+        let mut evaluations = 0;
+        let mut solution_changes = 0;
+        let mut stats = ConstructionStats::default();
+        let (mut total_constructed_cost, mut total_improvement) = (0.0, 0.0);
+
+        while start.elapsed().as_nanos() < self.max_time && !tracer.is_cancelled() {
+            let constructed = self.construct(&first_stage, rng);
+            let constructed_cost = self.instance.evaluate(&constructed)?;
+            stats.constructions += 1;
+            if stats.best_constructed_cost.is_none_or(|best| constructed_cost < best) {
+                stats.best_constructed_cost = Some(constructed_cost);
+            }
+            total_constructed_cost += constructed_cost.to_f64();
+
+            let descent = self.config.local_search.run(self.instance, constructed, time_left(&start), tracer.get_cancellation(), rng);
+            evaluations += descent.evaluations + 1;
+            let mut candidate = descent.permutation;
+            let mut candidate_cost = self.instance.evaluate(&candidate)?;
+            total_improvement += constructed_cost.to_f64() - candidate_cost.to_f64();
+
+            if !elite.is_empty() {
+                let (guide, _) = &elite[rng.gen_range(0..elite.len())];
+                let (relinked, relinked_cost) = self.relink(&candidate, guide)?;
+                evaluations += candidate.len();
+                if relinked_cost < candidate_cost {
+                    let descent = self.config.local_search.run(self.instance, relinked, time_left(&start), tracer.get_cancellation(), rng);
+                    evaluations += descent.evaluations;
+                    candidate_cost = self.instance.evaluate(&descent.permutation)?;
+                    candidate = descent.permutation;
+                }
+            }
+
+            if candidate_cost < best_cost {
+                best_cost = candidate_cost;
+                best.clone_from(&candidate);
+                solution_changes += 1;
+            }
+            update_elite(&mut elite, self.config.elite_size, candidate, candidate_cost);
+            tracer.record(evaluations, candidate_cost.to_f64(), best_cost.to_f64());
+        }
+
+        if stats.constructions > 0 {
+            stats.mean_constructed_cost = total_constructed_cost / stats.constructions as f64;
+            stats.mean_improvement = total_improvement / stats.constructions as f64;
+        }
+        self.stats = stats;
+
+        Ok(Solution {
+            permutation: best,
+            evaluations,
+            solution_changes,
+            trace: tracer.finish(evaluations, best_cost.to_f64(), best_cost.to_f64()),
+        })
+    }
+
+    fn get_name(&self) -> String {
+        "GraspSolver".to_string()
+    }
+
+    fn get_instance(&self) -> &Instance<C> {
+        self.instance
+    }
+
+    fn set_time_limit(&mut self, time_limit: u128) {
+        self.max_time = time_limit;
+    }
+
+    fn get_time_limit(&self) -> u128 {
+        self.max_time
+    }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
+}
+
+/// Keeps the `capacity` cheapest distinct solutions.
+fn update_elite<C: Cost>(elite: &mut Vec<(Vec<usize>, C)>, capacity: usize, candidate: Vec<usize>, cost: C) {
+    if capacity == 0 || elite.iter().any(|(perm, _)| *perm == candidate) {
+        return;
+    }
+    if elite.len() < capacity {
+        elite.push((candidate, cost));
+    } else if let Some(worst) = elite.iter_mut().max_by(|(_, first), (_, second)| cmp_costs(first, second)) {
+        if cost < worst.1 {
+            *worst = (candidate, cost);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::generator::{li_pardalos, LipaKind};
+
+    fn constructions(instance: &Instance, alpha: f64) -> HashSet<Vec<usize>> {
+        let solver = GraspSolver::new(instance, u128::MAX, GraspConfig { alpha, ..GraspConfig::default() });
+        let first_stage = solver.first_stage_candidates();
+        (0..30).map(|seed| solver.construct(&first_stage, &mut StdRng::seed_from_u64(seed))).collect()
+    }
+
+    #[test]
+    fn restricted_candidate_list_follows_alpha() {
+        let instance = li_pardalos(10, LipaKind::A, 2);
+        let greedy = GraspSolver::new(&instance, u128::MAX, GraspConfig { alpha: 0.0, ..GraspConfig::default() });
+        let random = GraspSolver::new(&instance, u128::MAX, GraspConfig { alpha: 1.0, ..GraspConfig::default() });
+        for candidates in [1, 2, 17, 100] {
+            assert_eq!(1, greedy.rcl_size(candidates));
+            assert_eq!(candidates, random.rcl_size(candidates));
+        }
+
+        // Greedy constructions only differ in the orientation of the first pair.
+        assert!(constructions(&instance, 0.0).len() <= 2);
+        assert!(constructions(&instance, 1.0).len() > 20);
+    }
+}
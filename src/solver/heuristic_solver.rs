@@ -1,30 +1,55 @@
-use crate::{argsort, instance::Instance};
+use rand::rngs::StdRng;
 
-use super::{Solution, Solver, SolvingError};
+use crate::{
+    argsort,
+    cost::Cost,
+    instance::{Formulation, Instance},
+};
 
-pub struct HeuristicSolver<'a> {
-    instance: &'a Instance,
+use super::{
+    monitor::Monitor,
+    trace::Tracer,
+    Solution, Solver, SolvingError,
+};
+
+pub struct HeuristicSolver<'a, C: Cost = usize> {
+    instance: &'a Instance<C>,
+    monitor: Monitor,
 }
 
-impl<'a> HeuristicSolver<'a> {
-    pub fn new(instance: &Instance) -> HeuristicSolver {
-        HeuristicSolver { instance }
+impl<'a, C: Cost> HeuristicSolver<'a, C> {
+    pub fn new(instance: &Instance<C>) -> HeuristicSolver<'_, C> {
+        HeuristicSolver {
+            instance,
+            monitor: Monitor::default(),
+        }
     }
 }
 
-impl<'a> Solver for HeuristicSolver<'a> {
-    fn solve(&mut self, _initial_solution: Vec<usize>) -> Result<Solution, SolvingError> {
-        let a_rows_sums: Vec<usize> = self
+impl<'a, C: Cost> Solver<C> for HeuristicSolver<'a, C> {
+    fn solve(
+        &mut self,
+        _initial_solution: Vec<usize>,
+        _rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        if let Formulation::Lawler(_) = self.instance.get_formulation() {
+            return Err(SolvingError {
+                message: "HeuristicSolver needs the flow and distance matrices, which Lawler instances lack"
+                    .to_string(),
+            });
+        }
+        let tracer = Tracer::new(&self.monitor);
+        let a_rows_sums: Vec<C::Wide> = self
             .instance
             .matrix_a
-            .iter()
-            .map(|row| row.iter().sum())
+            .rows()
+            .map(|row| row.iter().map(|x| x.widen()).sum())
             .collect();
-        let b_rows_sums: Vec<usize> = self
+        let b_rows_sums: Vec<C::Wide> = self
             .instance
             .matrix_b
-            .iter()
-            .map(|row| row.iter().sum())
+            .rows()
+            .map(|row| row.iter().map(|x| x.widen()).sum())
             .collect();
 
         let sorted_indices_a = argsort(&a_rows_sums, true);
@@ -34,11 +59,14 @@ impl<'a> Solver for HeuristicSolver<'a> {
         for i in 0..a_rows_sums.len() {
             permuatation[sorted_indices_a[i]] = sorted_indices_b[i];
         }
+        self.instance.repair(&mut permuatation);
+        let cost = self.instance.evaluate(&permuatation)?.to_f64();
 
         Ok(Solution {
             permutation: permuatation,
             evaluations: 0,
             solution_changes: 0,
+            trace: tracer.finish(0, cost, cost),
         })
     }
 
@@ -46,13 +74,32 @@ impl<'a> Solver for HeuristicSolver<'a> {
         "HeuristicSolver".to_string()
     }
 
-    fn get_instance(&self) -> &Instance {
+    fn get_instance(&self) -> &Instance<C> {
         self.instance
     }
 
-    fn set_time_limit(&mut self, time_limit: u128) {}
+    fn set_time_limit(&mut self, _time_limit: u128) {}
 
     fn get_time_limit(&self) -> u128 {
         0
     }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::matrix::LawlerCosts;
+
+    #[test]
+    fn lawler_instances_are_rejected() {
+        let lawler = Instance::from_lawler_costs(LawlerCosts::from_flat(2, (0..16).collect()), 0, vec![]);
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(HeuristicSolver::new(&lawler).solve(vec![0, 1], &mut rng).is_err());
+    }
 }
@@ -0,0 +1,261 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+use crate::{cost::Cost, get_random_pair, instance::Instance};
+
+use super::{
+    local_search::LocalSearch,
+    monitor::Monitor,
+    trace::Tracer,
+    Solution, Solver, SolvingError,
+};
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Perturbation {
+    /// `k` random swaps.
+    RandomSwaps { k: usize },
+    /// Shuffles the locations of `length` consecutive facilities.
+    SegmentShuffle { length: usize },
+}
+
+/// Decides whether the search continues from the new local optimum or from the current one.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Acceptance {
+    /// Only strictly better local optima.
+    Better,
+    /// Every local optimum.
+    RandomWalk,
+    /// Better local optima; after `iterations` iterations without a new best solution the
+    /// search restarts from a random permutation.
+    RestartAfter { iterations: usize },
+    /// Worse local optima with probability `exp(-increase / temperature)`; the temperature
+    /// is multiplied by `alpha` after every iteration.
+    Annealing { initial_temperature: f64, alpha: f64 },
+}
+
+impl Acceptance {
+    /// Whether the search moves from a local optimum of `current_cost` to one of
+    /// `candidate_cost`, at `temperature` for `Annealing`.
+    fn accepts<R: Rng + ?Sized>(&self, current_cost: f64, candidate_cost: f64, temperature: f64, rng: &mut R) -> bool {
+        match self {
+            Acceptance::Better | Acceptance::RestartAfter { .. } => candidate_cost < current_cost,
+            Acceptance::RandomWalk => true,
+            Acceptance::Annealing { .. } => {
+                let increase = candidate_cost - current_cost;
+                increase <= 0.0 || rng.gen::<f64>() < (-increase / temperature).exp()
+            }
+        }
+    }
+
+    /// Whether the search starts over after `iterations_without_improvement` iterations.
+    fn restarts(&self, iterations_without_improvement: usize) -> bool {
+        matches!(*self, Acceptance::RestartAfter { iterations } if iterations_without_improvement >= iterations)
+    }
+}
+
+pub struct IteratedLocalSearchSolver<'a, C: Cost = usize> {
+    instance: &'a Instance<C>,
+    max_time: u128,
+    monitor: Monitor,
+    local_search: LocalSearch,
+    perturbation: Perturbation,
+    acceptance: Acceptance,
+}
+
+impl<'a, C: Cost> IteratedLocalSearchSolver<'a, C> {
+    pub fn new(
+        instance: &Instance<C>,
+        max_time: u128,
+        local_search: LocalSearch,
+        perturbation: Perturbation,
+        acceptance: Acceptance,
+    ) -> IteratedLocalSearchSolver<'_, C> {
+        IteratedLocalSearchSolver {
+            instance,
+            max_time,
+            monitor: Monitor::default(),
+            local_search,
+            perturbation,
+            acceptance,
+        }
+    }
+
+    fn perturb(&self, mut perm: Vec<usize>, rng: &mut StdRng) -> Vec<usize> {
+        let n = perm.len();
+        if n < 2 {
+            return perm;
+        }
+        match self.perturbation {
+            Perturbation::RandomSwaps { k } => {
+                for _ in 0..k {
+                    let (i, j) = get_random_pair(n, rng);
+                    perm.swap(i, j);
+                }
+            }
+            Perturbation::SegmentShuffle { length } => {
+                let length = length.clamp(2, n);
+                let from = rng.gen_range(0..=n - length);
+                perm[from..from + length].shuffle(rng);
+            }
+        }
+        self.instance.repair(&mut perm);
+        perm
+    }
+}
+
+impl<'a, C: Cost> Solver<C> for IteratedLocalSearchSolver<'a, C> {
+    fn solve(
+        &mut self,
+        initial_solution: Vec<usize>,
+        rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        let start = std::time::Instant::now();
+        let time_left = |start: &std::time::Instant| self.max_time.saturating_sub(start.elapsed().as_nanos());
+        let mut tracer = Tracer::new(&self.monitor);
+
+        let descent = self.local_search.run(self.instance, initial_solution, time_left(&start), tracer.get_cancellation(), rng);
+        let mut evaluations = descent.evaluations;
+        let mut current = descent.permutation;
+        let mut current_cost = self.instance.evaluate(&current)?;
+        let mut best = current.clone();
+        let mut best_cost = current_cost;
+        let mut solution_changes = 0;
+        let mut iterations_without_improvement = 0;
+        let mut temperature = match self.acceptance {
+            Acceptance::Annealing { initial_temperature, .. } => initial_temperature,
+            _ => 0.0,
+        };
+        tracer.record(evaluations, current_cost.to_f64(), best_cost.to_f64());
+
+        while start.elapsed().as_nanos() < self.max_time && !tracer.is_cancelled() {
+            let candidate = self.perturb(current.clone(), rng);
+            let descent = self.local_search.run(self.instance, candidate, time_left(&start), tracer.get_cancellation(), rng);
+            evaluations += descent.evaluations;
+            let candidate = descent.permutation;
+            let candidate_cost = self.instance.evaluate(&candidate)?;
+
+            if candidate_cost < best_cost {
+                best.clone_from(&candidate);
+                best_cost = candidate_cost;
+                solution_changes += 1;
+                iterations_without_improvement = 0;
+            } else {
+                iterations_without_improvement += 1;
+            }
+
+            if self.acceptance.accepts(current_cost.to_f64(), candidate_cost.to_f64(), temperature, rng) {
+                current = candidate;
+                current_cost = candidate_cost;
+            }
+            if let Acceptance::Annealing { alpha, .. } = self.acceptance {
+                temperature *= alpha;
+            }
+
+            if self.acceptance.restarts(iterations_without_improvement) {
+                let restart = self.instance.random_permutation(rng);
+                let descent = self.local_search.run(self.instance, restart, time_left(&start), tracer.get_cancellation(), rng);
+                evaluations += descent.evaluations;
+                current = descent.permutation;
+                current_cost = self.instance.evaluate(&current)?;
+                iterations_without_improvement = 0;
+                if current_cost < best_cost {
+                    best.clone_from(&current);
+                    best_cost = current_cost;
+                    solution_changes += 1;
+                }
+            }
+            tracer.record(evaluations, current_cost.to_f64(), best_cost.to_f64());
+        }
+
+        Ok(Solution {
+            permutation: best,
+            evaluations,
+            solution_changes,
+            trace: tracer.finish(evaluations, current_cost.to_f64(), best_cost.to_f64()),
+        })
+    }
+
+    fn get_name(&self) -> String {
+        "IteratedLocalSearchSolver".to_string()
+    }
+
+    fn get_instance(&self) -> &Instance<C> {
+        self.instance
+    }
+
+    fn set_time_limit(&mut self, time_limit: u128) {
+        self.max_time = time_limit;
+    }
+
+    fn get_time_limit(&self) -> u128 {
+        self.max_time
+    }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{
+        generator::{li_pardalos, LipaKind},
+        solver::trace::{TracePoint, Tracing},
+    };
+
+    #[test]
+    fn criteria_accept_their_local_optima() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for acceptance in [Acceptance::Better, Acceptance::RestartAfter { iterations: 3 }] {
+            assert!(acceptance.accepts(10.0, 9.0, 0.0, &mut rng));
+            assert!(!acceptance.accepts(10.0, 10.0, 0.0, &mut rng));
+            assert!(!acceptance.accepts(10.0, 11.0, 0.0, &mut rng));
+        }
+        assert!(Acceptance::RandomWalk.accepts(10.0, 100.0, 0.0, &mut rng));
+
+        assert!(!Acceptance::Better.restarts(100));
+        let restart = Acceptance::RestartAfter { iterations: 3 };
+        assert!(!restart.restarts(2) && restart.restarts(3));
+    }
+
+    #[test]
+    fn annealing_accepts_worse_optima_with_the_boltzmann_probability() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let acceptance = Acceptance::Annealing { initial_temperature: 1.0, alpha: 1.0 };
+        assert!(acceptance.accepts(10.0, 9.0, 1e-9, &mut rng));
+        assert!(!acceptance.accepts(10.0, 11.0, 1e-9, &mut rng));
+
+        let trials = 20_000;
+        let accepted = (0..trials).filter(|_| acceptance.accepts(10.0, 12.0, 2.0, &mut rng)).count();
+        assert!((accepted as f64 / trials as f64 - (-1f64).exp()).abs() < 0.02);
+    }
+
+    #[test]
+    fn better_acceptance_never_moves_to_worse_optima() {
+        let instance = li_pardalos(12, LipaKind::B, 7);
+        let run = |acceptance| {
+            let mut solver = IteratedLocalSearchSolver::new(
+                &instance,
+                20_000_000,
+                LocalSearch::Greedy,
+                Perturbation::RandomSwaps { k: 4 },
+                acceptance,
+            );
+            solver.set_monitor(Monitor::new().with_tracing(Tracing::Interval(1)));
+            let mut rng = StdRng::seed_from_u64(3);
+            solver.solve(instance.random_permutation(&mut rng), &mut rng).unwrap().trace
+        };
+        let rises = |trace: &[TracePoint]| {
+            trace.windows(2).filter(|pair| pair[1].current_cost > pair[0].current_cost).count()
+        };
+
+        assert_eq!(0, rises(&run(Acceptance::Better)));
+        assert!(rises(&run(Acceptance::RandomWalk)) > 0);
+        assert!(rises(&run(Acceptance::RestartAfter { iterations: 2 })) > 0);
+    }
+}
@@ -0,0 +1,182 @@
+use rand::rngs::StdRng;
+
+use crate::{
+    cost::{cmp_costs, Cost},
+    instance::{Formulation, Instance},
+    lap,
+    matrix::{LawlerCosts, Matrix},
+};
+
+use super::{
+    monitor::Monitor,
+    trace::Tracer,
+    Solution, Solver, SolvingError,
+};
+
+/// Constructive heuristic that places every facility by solving one linear assignment
+/// problem. Placing facility `i` at location `k` is estimated to cost
+/// `(out_flow(i) * out_distance(k) + in_flow(i) * in_distance(k)) / (n - 1) + a[i][i] * b[k][k]`,
+/// built from the off-diagonal row and column sums of both matrices, plus the installation
+/// cost `c[i][k]` of instances with a linear term. On Lawler instances the same estimate is
+/// `(sum of d[i][j][k][l] + d[j][i][l][k] over j != i, l != k) / (n - 1) + d[i][i][k][k]`.
+///
+/// Unlike `HeuristicSolver`, which matches row sums by sorting, the estimate also accounts
+/// for incoming flows, so it is not limited to symmetric instances.
+pub struct LinearAssignmentSolver<'a, C: Cost = usize> {
+    instance: &'a Instance<C>,
+    monitor: Monitor,
+}
+
+impl<'a, C: Cost> LinearAssignmentSolver<'a, C> {
+    pub fn new(instance: &Instance<C>) -> LinearAssignmentSolver<'_, C> {
+        LinearAssignmentSolver {
+            instance,
+            monitor: Monitor::default(),
+        }
+    }
+
+    fn estimated_costs(&self) -> Vec<Vec<C::Wide>> {
+        if let Formulation::Lawler(costs) = self.instance.get_formulation() {
+            return LinearAssignmentSolver::lawler_estimated_costs(costs);
+        }
+        let a = &self.instance.matrix_a;
+        let b = &self.instance.matrix_b;
+        let n = self.instance.size;
+        let row_sums = |matrix: &Matrix<C>| -> Vec<C::Wide> {
+            (0..n).map(|i| (0..n).filter(|&j| j != i).map(|j| matrix[i][j].widen()).sum()).collect()
+        };
+        let col_sums = |matrix: &Matrix<C>| -> Vec<C::Wide> {
+            (0..n).map(|j| (0..n).filter(|&i| i != j).map(|i| matrix[i][j].widen()).sum()).collect()
+        };
+        let linear = |i: usize, k: usize| {
+            self.instance.get_linear_costs().map_or(C::Wide::default(), |costs| costs[i][k].widen())
+        };
+        let others = C::from_usize(n.saturating_sub(1)).widen();
+        let (out_flow, in_flow) = (row_sums(a), col_sums(a));
+        let (out_distance, in_distance) = (row_sums(b), col_sums(b));
+
+        (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|k| {
+                        out_flow[i] * out_distance[k]
+                            + in_flow[i] * in_distance[k]
+                            + (a[i][i].widen() * b[k][k].widen() + linear(i, k)) * others
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn lawler_estimated_costs(costs: &LawlerCosts<C>) -> Vec<Vec<C::Wide>> {
+        let n = costs.get_size();
+        let others = C::from_usize(n.saturating_sub(1)).widen();
+        (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|k| {
+                        let pairs: C::Wide = (0..n)
+                            .filter(|&j| j != i)
+                            .flat_map(|j| (0..n).filter(|&l| l != k).map(move |l| (j, l)))
+                            .map(|(j, l)| costs.get(i, j, k, l).widen() + costs.get(j, i, l, k).widen())
+                            .sum();
+                        pairs + costs.get(i, i, k, k).widen() * others
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl<'a, C: Cost> Solver<C> for LinearAssignmentSolver<'a, C> {
+    fn solve(
+        &mut self,
+        _initial_solution: Vec<usize>,
+        _rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        let tracer = Tracer::new(&self.monitor);
+        let mut costs = self.estimated_costs();
+        // A forbidden pair costs more than any assignment without one, so the LAP avoids it
+        // whenever the constraints can be met. Reducing every row to a minimum of zero keeps
+        // the optimal assignment and bounds such assignments by the sum of the row maxima.
+        let constraints = self.instance.get_constraints();
+        if !constraints.is_empty() {
+            for row in costs.iter_mut() {
+                let min = row.iter().copied().min_by(cmp_costs).unwrap_or_default();
+                row.iter_mut().for_each(|cost| *cost -= min);
+            }
+            let row_maxima: Vec<C::Wide> =
+                costs.iter().map(|row| row.iter().copied().max_by(cmp_costs).unwrap_or_default()).collect();
+            let total: f64 = row_maxima.iter().map(|&max| C::wide_to_f64(max) + 1.0).sum();
+            if (costs.len() + 1) as f64 * total >= C::WIDE_MAX {
+                return Err(SolvingError {
+                    message: "LinearAssignmentSolver cannot penalise forbidden pairs of costs this large".to_string(),
+                });
+            }
+            let penalty = row_maxima.into_iter().sum::<C::Wide>() + C::from_usize(1).widen();
+            for (i, row) in costs.iter_mut().enumerate() {
+                for (k, cost) in row.iter_mut().enumerate() {
+                    if !constraints.allows(i, k) {
+                        *cost = penalty;
+                    }
+                }
+            }
+        }
+        let mut assignment = lap::solve(&costs);
+        self.instance.repair(&mut assignment.columns);
+        let cost = self.instance.evaluate(&assignment.columns)?.to_f64();
+
+        Ok(Solution {
+            permutation: assignment.columns,
+            evaluations: 0,
+            solution_changes: 0,
+            trace: tracer.finish(0, cost, cost),
+        })
+    }
+
+    fn get_name(&self) -> String {
+        "LinearAssignmentSolver".to_string()
+    }
+
+    fn get_instance(&self) -> &Instance<C> {
+        self.instance
+    }
+
+    fn set_time_limit(&mut self, _time_limit: u128) {}
+
+    fn get_time_limit(&self) -> u128 {
+        0
+    }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::generator::{li_pardalos, LipaKind};
+
+    #[test]
+    fn lawler_estimates_match_their_koopmans_beckmann_form() {
+        let instance = li_pardalos(8, LipaKind::B, 6);
+        let n = instance.get_size();
+        let (a, b) = (&instance.matrix_a, &instance.matrix_b);
+        let flat = (0..n.pow(4))
+            .map(|idx| a[idx / n.pow(3)][idx / n.pow(2) % n] * b[idx / n % n][idx % n])
+            .collect();
+        let lawler = Instance::from_lawler_costs(LawlerCosts::from_flat(n, flat), 0, vec![]);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let expected = LinearAssignmentSolver::new(&instance).solve(vec![], &mut rng).unwrap();
+        let solution = LinearAssignmentSolver::new(&lawler).solve(vec![], &mut rng).unwrap();
+        assert_eq!(
+            LinearAssignmentSolver::new(&instance).estimated_costs(),
+            LinearAssignmentSolver::new(&lawler).estimated_costs()
+        );
+        assert_eq!(expected.permutation, solution.permutation);
+    }
+}
@@ -1,2 +1,42 @@
+use rand::rngs::StdRng;
+use serde::Deserialize;
+
+use crate::{cost::Cost, instance::Instance};
+
+use super::{
+    monitor::{CancellationToken, Monitor},
+    Solution, Solver,
+};
+
 pub mod greedy;
 pub mod steepest;
+
+/// Local search used inside other metaheuristics.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalSearch {
+    Greedy,
+    Steepest,
+}
+
+impl LocalSearch {
+    /// Descends from `perm` to a local optimum, or until `max_time` runs out or `cancellation`
+    /// is cancelled.
+    pub fn run<C: Cost>(
+        &self,
+        instance: &Instance<C>,
+        perm: Vec<usize>,
+        max_time: u128,
+        cancellation: Option<&CancellationToken>,
+        rng: &mut StdRng,
+    ) -> Solution {
+        let mut solver: Box<dyn Solver<C> + '_> = match self {
+            LocalSearch::Greedy => Box::new(greedy::GreedySolver::new(instance, max_time)),
+            LocalSearch::Steepest => Box::new(steepest::SteepestSolver::new(instance, max_time)),
+        };
+        if let Some(cancellation) = cancellation {
+            solver.set_monitor(Monitor::new().with_cancellation(cancellation.clone()));
+        }
+        solver.solve(perm, rng).expect("Local search should not fail")
+    }
+}
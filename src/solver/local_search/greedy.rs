@@ -1,65 +1,109 @@
+use rand::rngs::StdRng;
+
 use crate::{
+    cost::Cost,
     get_random_permutation,
     instance::Instance,
     solver::{
-        compute_num_neighbours, eval_diff, move_to_neighbour, Solution, Solver, SolvingError,
+        neighbourhood::{solver_name, Neighbourhood, Swap},
+        monitor::Monitor,
+        trace::Tracer,
+        Solution, Solver, SolvingError,
     },
 };
 
-pub struct GreedySolver<'a> {
-    instance: &'a Instance,
+pub struct GreedySolver<'a, C: Cost = usize, N: Neighbourhood = Swap> {
+    instance: &'a Instance<C>,
     max_time: u128,
+    monitor: Monitor,
+    neighbourhood: N,
 }
 
-impl<'a> GreedySolver<'a> {
-    pub fn new(instance: &Instance, max_time: u128) -> GreedySolver {
-        GreedySolver { instance, max_time }
+impl<'a, C: Cost> GreedySolver<'a, C> {
+    pub fn new(instance: &Instance<C>, max_time: u128) -> GreedySolver<'_, C> {
+        GreedySolver::with_neighbourhood(instance, max_time, Swap)
+    }
+}
+
+impl<'a, C: Cost, N: Neighbourhood> GreedySolver<'a, C, N> {
+    pub fn with_neighbourhood(instance: &Instance<C>, max_time: u128, neighbourhood: N) -> GreedySolver<'_, C, N> {
+        GreedySolver {
+            instance,
+            max_time,
+            monitor: Monitor::default(),
+            neighbourhood,
+        }
     }
 }
 
-impl<'a> Solver for GreedySolver<'a> {
-    fn solve(&mut self, mut starting_perm: Vec<usize>) -> Result<Solution, SolvingError> {
-        let num_neighbours = compute_num_neighbours(starting_perm.len());
+impl<'a, C: Cost, N: Neighbourhood> Solver<C> for GreedySolver<'a, C, N> {
+    fn solve(
+        &mut self,
+        mut starting_perm: Vec<usize>,
+        rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        self.instance.get_constraints().check(&starting_perm)?;
+        let n = starting_perm.len();
+        let num_neighbours = self.neighbourhood.size(n);
 
         let mut solutions_evaluated = 0;
         let mut solutions_changes = 0;
+        let mut cost = self.instance.evaluate_wide(&starting_perm);
+        let mut tracer = Tracer::new(&self.monitor);
+        tracer.record(0, C::wide_to_f64(cost), C::wide_to_f64(cost));
+        if num_neighbours == 0 {
+            return Ok(Solution {
+                permutation: starting_perm,
+                evaluations: 0,
+                solution_changes: 0,
+                trace: tracer.finish(0, C::wide_to_f64(cost), C::wide_to_f64(cost)),
+            });
+        }
         let start = std::time::Instant::now();
 
         loop {
-            let mut found_improvement = false;
-            let mut best_neighbour_idx = 0;
+            let mut improving_move = None;
 
-            for neighbour_idx in get_random_permutation(num_neighbours) {
-                let diff = eval_diff(self.instance, &starting_perm, neighbour_idx);
+            for neighbour_idx in get_random_permutation(num_neighbours, rng) {
+                let mv = self.neighbourhood.nth_move(n, neighbour_idx);
+                if !self.neighbourhood.is_feasible(self.instance, &starting_perm, mv) {
+                    continue;
+                }
+                let diff = self.neighbourhood.delta(self.instance, &starting_perm, mv);
                 solutions_evaluated += 1;
 
-                if diff > 0 || start.elapsed().as_nanos() > self.max_time {
-                    best_neighbour_idx = neighbour_idx;
-                    found_improvement = true;
+                if diff > C::Wide::default() || start.elapsed().as_nanos() > self.max_time || tracer.is_cancelled() {
+                    improving_move = Some((mv, diff));
                     break;
                 }
             }
 
-            if !found_improvement || start.elapsed().as_nanos() > self.max_time {
+            let Some((mv, diff)) = improving_move else {
+                break;
+            };
+            if start.elapsed().as_nanos() > self.max_time || tracer.is_cancelled() {
                 break;
             }
 
-            starting_perm = move_to_neighbour(starting_perm, best_neighbour_idx);
+            self.neighbourhood.apply(&mut starting_perm, mv);
             solutions_changes += 1;
+            cost -= diff;
+            tracer.record(solutions_evaluated, C::wide_to_f64(cost), C::wide_to_f64(cost));
         }
 
         Ok(Solution {
             permutation: starting_perm,
             evaluations: solutions_evaluated,
             solution_changes: solutions_changes,
+            trace: tracer.finish(solutions_evaluated, C::wide_to_f64(cost), C::wide_to_f64(cost)),
         })
     }
 
     fn get_name(&self) -> String {
-        "GreedySolver".to_string()
+        solver_name("GreedySolver", &self.neighbourhood)
     }
 
-    fn get_instance(&self) -> &Instance {
+    fn get_instance(&self) -> &Instance<C> {
         self.instance
     }
 
@@ -70,4 +114,8 @@ impl<'a> Solver for GreedySolver<'a> {
     fn get_time_limit(&self) -> u128 {
         self.max_time
     }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
 }
@@ -1,84 +1,119 @@
-use std::cmp::max;
-use rand::random;
+use rand::{rngs::StdRng, Rng};
 
 use crate::{
+    cost::Cost,
     instance::Instance,
-    solver::{compute_num_neighbours, eval_diff, move_to_neighbour, Result, Solution, Solver},
+    solver::{
+        neighbourhood::{solver_name, Neighbourhood, Swap},
+        monitor::Monitor,
+        trace::Tracer,
+        Result, Solution, Solver,
+    },
 };
 
-pub struct SteepestSolver<'a> {
-    instance: &'a Instance,
+/// Number of scanned moves between two checks of the time limit and cancellation.
+const CHECK_INTERVAL: usize = 256;
+
+pub struct SteepestSolver<'a, C: Cost = usize, N: Neighbourhood = Swap> {
+    instance: &'a Instance<C>,
     max_time: u128,
+    monitor: Monitor,
+    neighbourhood: N,
 }
 
-impl<'a> SteepestSolver<'a> {
-    pub fn new(instance: &Instance, max_time: u128) -> SteepestSolver {
-        SteepestSolver { instance, max_time }
+impl<'a, C: Cost> SteepestSolver<'a, C> {
+    pub fn new(instance: &Instance<C>, max_time: u128) -> SteepestSolver<'_, C> {
+        SteepestSolver::with_neighbourhood(instance, max_time, Swap)
+    }
+}
+
+impl<'a, C: Cost, N: Neighbourhood> SteepestSolver<'a, C, N> {
+    pub fn with_neighbourhood(instance: &Instance<C>, max_time: u128, neighbourhood: N) -> SteepestSolver<'_, C, N> {
+        SteepestSolver {
+            instance,
+            max_time,
+            monitor: Monitor::default(),
+            neighbourhood,
+        }
     }
 }
 
-impl<'a> Solver for SteepestSolver<'a> {
-    fn solve(&mut self, mut starting_perm: Vec<usize>) -> Result<Solution> {
+impl<'a, C: Cost, N: Neighbourhood> Solver<C> for SteepestSolver<'a, C, N> {
+    fn solve(&mut self, mut starting_perm: Vec<usize>, rng: &mut StdRng) -> Result<Solution> {
+        self.instance.get_constraints().check(&starting_perm)?;
         let max_plateau_moves = 10;
-        let num_neighbours = compute_num_neighbours(starting_perm.len());
+        let n = starting_perm.len();
+        let num_neighbours = self.neighbourhood.size(n);
 
         let mut solutions_evaluated = 0;
+        let mut moves_scanned: usize = 0;
         let mut solutions_changes = 0;
-        let mut best_neighbours_num = 0;
-        let mut best_neighbours = vec![0; num_neighbours];
-        let mut best_neighbour_diff = 0;
+        let mut best_neighbours = Vec::new();
+        let zero = C::Wide::default();
+        let mut best_neighbour_diff = zero;
+        let mut cost = self.instance.evaluate_wide(&starting_perm);
+        let mut tracer = Tracer::new(&self.monitor);
+        tracer.record(0, C::wide_to_f64(cost), C::wide_to_f64(cost));
         let start = std::time::Instant::now();
+        let mut deltas = self.neighbourhood.init_deltas(self.instance, &starting_perm);
 
         let mut plateau_moves = 0;
-        loop {
+        'search: loop {
             for neighbour_idx in 0..num_neighbours {
-                match eval_diff(self.instance, &starting_perm, neighbour_idx) {
-                    diff if diff == best_neighbour_diff && best_neighbour_diff == 0 => {
-                        best_neighbours_num += 1;
-                        best_neighbours[best_neighbours_num - 1] = neighbour_idx;
-
-                    }
-                    diff if diff > best_neighbour_diff => {
-                        best_neighbour_diff = diff;
-                        best_neighbours[0] = neighbour_idx;
-                        best_neighbours_num = 1;
-                        plateau_moves = 0;
+                let mv = self.neighbourhood.nth_move(n, neighbour_idx);
+                if self.neighbourhood.is_feasible(self.instance, &starting_perm, mv) {
+                    match self.neighbourhood.cached_delta(&deltas, &starting_perm, mv) {
+                        diff if diff == best_neighbour_diff && best_neighbour_diff == zero => {
+                            best_neighbours.push(mv);
+                        }
+                        diff if diff > best_neighbour_diff => {
+                            best_neighbour_diff = diff;
+                            best_neighbours.clear();
+                            best_neighbours.push(mv);
+                            plateau_moves = 0;
+                        }
+                        _ => {}
                     }
-                    _ => {}
+                    solutions_evaluated += 1;
                 }
-                solutions_evaluated += 1;
-                if start.elapsed().as_nanos() > self.max_time {
-                    break;
+                moves_scanned += 1;
+                if moves_scanned.is_multiple_of(CHECK_INTERVAL)
+                    && (start.elapsed().as_nanos() > self.max_time || tracer.is_cancelled())
+                {
+                    break 'search;
                 }
             }
 
-            if best_neighbours_num == 0 || plateau_moves >= max_plateau_moves || start.elapsed().as_nanos() > self.max_time {
+            if best_neighbours.is_empty() || plateau_moves >= max_plateau_moves {
                 break;
             }
 
-            if best_neighbour_diff == 0 {
+            if best_neighbour_diff == zero {
                 plateau_moves += 1;
             }
 
-            let best_neighbour_idx = random::<usize>() % (best_neighbours_num);
-            best_neighbours_num = 0;
-            best_neighbour_diff = 0;
-            starting_perm = move_to_neighbour(starting_perm, best_neighbours[best_neighbour_idx]);
+            let mv = best_neighbours[rng.gen_range(0..best_neighbours.len())];
+            best_neighbours.clear();
+            cost -= best_neighbour_diff;
+            best_neighbour_diff = zero;
+            self.neighbourhood.apply_cached(&mut deltas, &mut starting_perm, mv);
             solutions_changes += 1;
+            tracer.record(solutions_evaluated, C::wide_to_f64(cost), C::wide_to_f64(cost));
         }
 
         Ok(Solution {
             permutation: starting_perm,
             evaluations: solutions_evaluated,
             solution_changes: solutions_changes,
+            trace: tracer.finish(solutions_evaluated, C::wide_to_f64(cost), C::wide_to_f64(cost)),
         })
     }
 
     fn get_name(&self) -> String {
-        "SteepestSolver".to_string()
+        solver_name("SteepestSolver", &self.neighbourhood)
     }
 
-    fn get_instance(&self) -> &Instance {
+    fn get_instance(&self) -> &Instance<C> {
         self.instance
     }
 
@@ -89,4 +124,8 @@ impl<'a> Solver for SteepestSolver<'a> {
     fn get_time_limit(&self) -> u128 {
         self.max_time
     }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
 }
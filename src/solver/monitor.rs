@@ -0,0 +1,127 @@
+//! Hooks for following a run from the outside and stopping it early.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::trace::{TracePoint, Tracing};
+
+/// Called with the state of the run whenever it finds a new best solution.
+pub type Observer = Arc<dyn Fn(&TracePoint) + Send + Sync>;
+
+/// Flag shared between a run and whoever may stop it, e.g. an interactive CLI or another
+/// thread. A cancelled run stops at its next step, as if its time limit had run out, and
+/// returns the best solution found so far.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// What a solver reports about its runs and who may stop them, set with `Solver::set_monitor`.
+#[derive(Clone, Default)]
+pub struct Monitor {
+    pub tracing: Tracing,
+    pub observer: Option<Observer>,
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl Monitor {
+    pub fn new() -> Monitor {
+        Monitor::default()
+    }
+
+    pub fn with_tracing(self, tracing: Tracing) -> Monitor {
+        Monitor { tracing, ..self }
+    }
+
+    pub fn with_observer(self, observer: impl Fn(&TracePoint) + Send + Sync + 'static) -> Monitor {
+        Monitor {
+            observer: Some(Arc::new(observer)),
+            ..self
+        }
+    }
+
+    pub fn with_cancellation(self, cancellation: CancellationToken) -> Monitor {
+        Monitor {
+            cancellation: Some(cancellation),
+            ..self
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.as_ref().is_some_and(CancellationToken::is_cancelled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread};
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        generator::{li_pardalos, LipaKind},
+        solver::{
+            iterated_local_search::{Acceptance, IteratedLocalSearchSolver, Perturbation},
+            local_search::LocalSearch,
+            tabu_search::TabuSearchSolver,
+            Solver,
+        },
+    };
+
+    #[test]
+    fn observed_runs_stop_when_cancelled() {
+        let instance = li_pardalos(40, LipaKind::A, 5);
+        let solvers: Vec<Box<dyn Solver + Send>> = vec![
+            Box::new(TabuSearchSolver::new(&instance, usize::MAX, u128::MAX)),
+            Box::new(IteratedLocalSearchSolver::new(
+                &instance,
+                u128::MAX,
+                LocalSearch::Steepest,
+                Perturbation::RandomSwaps { k: 3 },
+                Acceptance::RandomWalk,
+            )),
+        ];
+        for mut solver in solvers {
+            let cancellation = CancellationToken::new();
+            let (sender, receiver) = mpsc::channel();
+            solver.set_monitor(
+                Monitor::new()
+                    .with_observer(move |point| {
+                        let _ = sender.send(point.best_cost);
+                    })
+                    .with_cancellation(cancellation.clone()),
+            );
+
+            let (mut best_costs, permutation) = thread::scope(|scope| {
+                let run = scope.spawn(|| {
+                    let mut rng = StdRng::seed_from_u64(3);
+                    let start = instance.random_permutation(&mut rng);
+                    solver.solve(start, &mut rng).unwrap().permutation
+                });
+                let first = receiver.recv().unwrap();
+                cancellation.cancel();
+                (vec![first], run.join().unwrap())
+            });
+            drop(solver);
+
+            best_costs.extend(receiver.iter());
+            assert!(best_costs.windows(2).all(|pair| pair[1] < pair[0]));
+            assert_eq!(instance.evaluate(&permutation).unwrap() as f64, *best_costs.last().unwrap());
+        }
+    }
+}
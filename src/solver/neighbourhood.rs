@@ -0,0 +1,546 @@
+use rand::Rng;
+
+use crate::{cost::Cost, get_random_pair, instance::Instance};
+
+use super::{
+    calculate_swap_indices, delta_matrix::{swap_delta, DeltaMatrix}, eval_diff_reassign, eval_diff_relocated,
+    rotate_three,
+};
+
+/// A move type of the local search solvers. Moves of a permutation of `n` facilities are
+/// numbered from 0 to `size(n) - 1`, so solvers can enumerate them in any order.
+///
+/// Deltas are `cost(perm) - cost(perm after move)`, so a positive value is an improving move.
+pub trait Neighbourhood {
+    type Move: Copy;
+
+    /// Keeps the deltas of all moves of the current permutation during full scans.
+    type Deltas<'i, C: Cost>;
+
+    fn get_name(&self) -> String;
+
+    fn size(&self, n: usize) -> usize;
+
+    fn nth_move(&self, n: usize, idx: usize) -> Self::Move;
+
+    /// Uniformly random move, or `None` when `n` facilities have no moves at all.
+    fn random_move<R: Rng + ?Sized>(&self, n: usize, rng: &mut R) -> Option<Self::Move> {
+        let size = self.size(n);
+        (size > 0).then(|| self.nth_move(n, rng.gen_range(0..size)))
+    }
+
+    fn delta<C: Cost>(&self, instance: &Instance<C>, perm: &[usize], mv: Self::Move) -> C::Wide;
+
+    fn apply(&self, perm: &mut [usize], mv: Self::Move);
+
+    fn undo(&self, perm: &mut [usize], mv: Self::Move);
+
+    fn init_deltas<'i, C: Cost>(&self, instance: &'i Instance<C>, perm: &[usize]) -> Self::Deltas<'i, C>;
+
+    fn cached_delta<C: Cost>(&self, deltas: &Self::Deltas<'_, C>, perm: &[usize], mv: Self::Move) -> C::Wide;
+
+    /// Applies `mv` to `perm` and brings `deltas` up to date.
+    fn apply_cached<C: Cost>(&self, deltas: &mut Self::Deltas<'_, C>, perm: &mut [usize], mv: Self::Move);
+
+    /// Whether `perm` still satisfies the constraints of `instance` after `mv`.
+    fn is_feasible<C: Cost>(&self, instance: &Instance<C>, perm: &[usize], mv: Self::Move) -> bool {
+        if instance.get_constraints().is_empty() {
+            return true;
+        }
+        let mut moved = perm.to_vec();
+        self.apply(&mut moved, mv);
+        instance.get_constraints().is_feasible(&moved)
+    }
+
+    /// Random move keeping `perm` feasible, or `None` when there is none. A few random moves
+    /// are drawn first; after that the moves are scanned from a random one.
+    fn random_feasible_move<C: Cost, R: Rng + ?Sized>(
+        &self,
+        instance: &Instance<C>,
+        perm: &[usize],
+        rng: &mut R,
+    ) -> Option<Self::Move> {
+        let n = perm.len();
+        let size = self.size(n);
+        if size == 0 {
+            return None;
+        }
+        for _ in 0..n {
+            let mv = self.random_move(n, rng)?;
+            if self.is_feasible(instance, perm, mv) {
+                return Some(mv);
+            }
+        }
+        let first = rng.gen_range(0..size);
+        (0..size)
+            .map(|offset| self.nth_move(n, (first + offset) % size))
+            .find(|&mv| self.is_feasible(instance, perm, mv))
+    }
+}
+
+/// Name of `solver` using `neighbourhood`; swap-based solvers keep their plain names.
+pub(crate) fn solver_name<N: Neighbourhood>(solver: &str, neighbourhood: &N) -> String {
+    match neighbourhood.get_name().as_str() {
+        "Swap" => solver.to_string(),
+        name => format!("{solver}-{name}"),
+    }
+}
+
+/// Exchange of the locations of two facilities. Full scans use Taillard's delta matrix.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Swap;
+
+impl Neighbourhood for Swap {
+    type Move = (usize, usize);
+    type Deltas<'i, C: Cost> = DeltaMatrix<'i, C>;
+
+    fn get_name(&self) -> String {
+        "Swap".to_string()
+    }
+
+    fn size(&self, n: usize) -> usize {
+        n * n.saturating_sub(1) / 2
+    }
+
+    fn nth_move(&self, n: usize, idx: usize) -> Self::Move {
+        calculate_swap_indices(n as isize, idx as isize)
+    }
+
+    fn random_move<R: Rng + ?Sized>(&self, n: usize, rng: &mut R) -> Option<Self::Move> {
+        (n >= 2).then(|| get_random_pair(n, rng))
+    }
+
+    fn delta<C: Cost>(&self, instance: &Instance<C>, perm: &[usize], (i, j): Self::Move) -> C::Wide {
+        swap_delta(instance, perm, i, j)
+    }
+
+    fn apply(&self, perm: &mut [usize], (i, j): Self::Move) {
+        perm.swap(i, j);
+    }
+
+    fn undo(&self, perm: &mut [usize], (i, j): Self::Move) {
+        perm.swap(i, j);
+    }
+
+    fn init_deltas<'i, C: Cost>(&self, instance: &'i Instance<C>, perm: &[usize]) -> Self::Deltas<'i, C> {
+        DeltaMatrix::new(instance, perm)
+    }
+
+    fn cached_delta<C: Cost>(&self, deltas: &Self::Deltas<'_, C>, _perm: &[usize], (i, j): Self::Move) -> C::Wide {
+        deltas.get(i, j)
+    }
+
+    fn apply_cached<C: Cost>(&self, deltas: &mut Self::Deltas<'_, C>, perm: &mut [usize], (i, j): Self::Move) {
+        deltas.apply_swap(perm, i, j);
+    }
+
+    /// Swaps of two dummy facilities of a rectangular instance never change the cost, so
+    /// they are not taken either; otherwise searches that accept non-improving moves stall on them.
+    fn is_feasible<C: Cost>(&self, instance: &Instance<C>, perm: &[usize], (i, j): Self::Move) -> bool {
+        if i.min(j) >= instance.get_facility_count() {
+            return false;
+        }
+        let constraints = instance.get_constraints();
+        constraints.allows(i, perm[j]) && constraints.allows(j, perm[i])
+    }
+}
+
+/// Swaps that move a real facility of a rectangular instance: either two facilities exchange
+/// their locations or a facility moves to an empty location. Swaps of two empty locations,
+/// which never change the cost, are left out; on square instances this is `Swap`.
+#[derive(Clone, Copy, Debug)]
+pub struct Relocation {
+    facilities: usize,
+}
+
+impl Relocation {
+    pub fn new<C: Cost>(instance: &Instance<C>) -> Relocation {
+        Relocation {
+            facilities: instance.get_facility_count(),
+        }
+    }
+}
+
+impl Neighbourhood for Relocation {
+    type Move = (usize, usize);
+    type Deltas<'i, C: Cost> = DeltaMatrix<'i, C>;
+
+    fn get_name(&self) -> String {
+        "Relocation".to_string()
+    }
+
+    fn size(&self, n: usize) -> usize {
+        let facilities = self.facilities.min(n);
+        facilities * (n - facilities) + Swap.size(facilities)
+    }
+
+    fn nth_move(&self, n: usize, idx: usize) -> Self::Move {
+        // Moves to each of the empty locations first, then the swaps of two facilities.
+        let facilities = self.facilities.min(n);
+        let relocations = facilities * (n - facilities);
+        if idx < relocations {
+            (idx / (n - facilities), facilities + idx % (n - facilities))
+        } else {
+            Swap.nth_move(facilities, idx - relocations)
+        }
+    }
+
+    fn delta<C: Cost>(&self, instance: &Instance<C>, perm: &[usize], mv: Self::Move) -> C::Wide {
+        Swap.delta(instance, perm, mv)
+    }
+
+    fn apply(&self, perm: &mut [usize], mv: Self::Move) {
+        Swap.apply(perm, mv);
+    }
+
+    fn undo(&self, perm: &mut [usize], mv: Self::Move) {
+        Swap.undo(perm, mv);
+    }
+
+    fn init_deltas<'i, C: Cost>(&self, instance: &'i Instance<C>, perm: &[usize]) -> Self::Deltas<'i, C> {
+        Swap.init_deltas(instance, perm)
+    }
+
+    fn cached_delta<C: Cost>(&self, deltas: &Self::Deltas<'_, C>, perm: &[usize], mv: Self::Move) -> C::Wide {
+        Swap.cached_delta(deltas, perm, mv)
+    }
+
+    fn apply_cached<C: Cost>(&self, deltas: &mut Self::Deltas<'_, C>, perm: &mut [usize], mv: Self::Move) {
+        Swap.apply_cached(deltas, perm, mv);
+    }
+
+    fn is_feasible<C: Cost>(&self, instance: &Instance<C>, perm: &[usize], mv: Self::Move) -> bool {
+        Swap.is_feasible(instance, perm, mv)
+    }
+}
+
+/// Rotation of three facilities: the move `(i, j, k)` gives `i` the location of `j`, `j` the
+/// location of `k` and `k` the location of `i`. Every triple has two distinct rotations.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreeCycle;
+
+impl Neighbourhood for ThreeCycle {
+    type Move = (usize, usize, usize);
+    type Deltas<'i, C: Cost> = &'i Instance<C>;
+
+    fn get_name(&self) -> String {
+        "ThreeCycle".to_string()
+    }
+
+    fn size(&self, n: usize) -> usize {
+        if n < 3 {
+            0
+        } else {
+            n * (n - 1) * (n - 2) / 3
+        }
+    }
+
+    fn nth_move(&self, n: usize, idx: usize) -> Self::Move {
+        // Triples i < j < k in lexicographic order, each followed by its reversed rotation.
+        let mut rest = idx / 2;
+        let mut i = 0;
+        while rest >= (n - i - 1) * (n - i - 2) / 2 {
+            rest -= (n - i - 1) * (n - i - 2) / 2;
+            i += 1;
+        }
+        let mut j = i + 1;
+        while rest >= n - j - 1 {
+            rest -= n - j - 1;
+            j += 1;
+        }
+        let k = j + 1 + rest;
+        if idx.is_multiple_of(2) {
+            (i, j, k)
+        } else {
+            (i, k, j)
+        }
+    }
+
+    fn random_move<R: Rng + ?Sized>(&self, n: usize, rng: &mut R) -> Option<Self::Move> {
+        if n < 3 {
+            return None;
+        }
+        let (i, j) = get_random_pair(n, rng);
+        let k = (0..n).filter(|&k| k != i && k != j).nth(rng.gen_range(0..n - 2)).unwrap();
+        Some((i, j, k))
+    }
+
+    fn delta<C: Cost>(&self, instance: &Instance<C>, perm: &[usize], (i, j, k): Self::Move) -> C::Wide {
+        eval_diff_reassign(instance, perm, &[i, j, k], &[perm[j], perm[k], perm[i]])
+    }
+
+    fn apply(&self, perm: &mut [usize], (i, j, k): Self::Move) {
+        rotate_three(perm, i, j, k);
+    }
+
+    fn undo(&self, perm: &mut [usize], (i, j, k): Self::Move) {
+        rotate_three(perm, i, k, j);
+    }
+
+    fn init_deltas<'i, C: Cost>(&self, instance: &'i Instance<C>, _perm: &[usize]) -> Self::Deltas<'i, C> {
+        instance
+    }
+
+    fn cached_delta<C: Cost>(&self, instance: &Self::Deltas<'_, C>, perm: &[usize], mv: Self::Move) -> C::Wide {
+        self.delta(instance, perm, mv)
+    }
+
+    fn apply_cached<C: Cost>(&self, _instance: &mut Self::Deltas<'_, C>, perm: &mut [usize], mv: Self::Move) {
+        self.apply(perm, mv);
+    }
+
+    fn is_feasible<C: Cost>(&self, instance: &Instance<C>, perm: &[usize], (i, j, k): Self::Move) -> bool {
+        let constraints = instance.get_constraints();
+        constraints.allows(i, perm[j]) && constraints.allows(j, perm[k]) && constraints.allows(k, perm[i])
+    }
+}
+
+/// Shift move: the facility at position `from` is removed and reinserted at position `to`,
+/// moving the facilities in between by one position. Its delta costs O(n * |from - to|).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Insertion;
+
+impl Insertion {
+    fn new_location(perm: &[usize], (from, to): (usize, usize), v: usize) -> usize {
+        if v < from.min(to) || v > from.max(to) {
+            perm[v]
+        } else if v == to {
+            perm[from]
+        } else if from < to {
+            perm[v + 1]
+        } else {
+            perm[v - 1]
+        }
+    }
+}
+
+impl Neighbourhood for Insertion {
+    type Move = (usize, usize);
+    type Deltas<'i, C: Cost> = &'i Instance<C>;
+
+    fn get_name(&self) -> String {
+        "Insertion".to_string()
+    }
+
+    fn size(&self, n: usize) -> usize {
+        n * n.saturating_sub(1)
+    }
+
+    fn nth_move(&self, n: usize, idx: usize) -> Self::Move {
+        let from = idx / (n - 1);
+        let to = idx % (n - 1);
+        (from, if to >= from { to + 1 } else { to })
+    }
+
+    fn random_move<R: Rng + ?Sized>(&self, n: usize, rng: &mut R) -> Option<Self::Move> {
+        (n >= 2).then(|| get_random_pair(n, rng))
+    }
+
+    fn delta<C: Cost>(&self, instance: &Instance<C>, perm: &[usize], (from, to): Self::Move) -> C::Wide {
+        let (low, high) = (from.min(to), from.max(to));
+        eval_diff_relocated(
+            instance,
+            perm,
+            low..=high,
+            |v| (low..=high).contains(&v),
+            |v| Insertion::new_location(perm, (from, to), v),
+        )
+    }
+
+    fn apply(&self, perm: &mut [usize], (from, to): Self::Move) {
+        if from < to {
+            perm[from..=to].rotate_left(1);
+        } else {
+            perm[to..=from].rotate_right(1);
+        }
+    }
+
+    fn undo(&self, perm: &mut [usize], (from, to): Self::Move) {
+        self.apply(perm, (to, from));
+    }
+
+    fn init_deltas<'i, C: Cost>(&self, instance: &'i Instance<C>, _perm: &[usize]) -> Self::Deltas<'i, C> {
+        instance
+    }
+
+    fn cached_delta<C: Cost>(&self, instance: &Self::Deltas<'_, C>, perm: &[usize], mv: Self::Move) -> C::Wide {
+        self.delta(instance, perm, mv)
+    }
+
+    fn apply_cached<C: Cost>(&self, _instance: &mut Self::Deltas<'_, C>, perm: &mut [usize], mv: Self::Move) {
+        self.apply(perm, mv);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        get_random_permutation,
+        solver::{
+            ant_colony::{AntSystemConfig, MaxMinAntSystemSolver},
+            grasp::{GraspConfig, GraspSolver},
+            local_search::{greedy::GreedySolver, steepest::SteepestSolver},
+            Solver,
+        },
+    };
+
+    fn check_moves<C: Cost, N: Neighbourhood>(neighbourhood: N, instance: &Instance<C>, rng: &mut StdRng) {
+        let n = instance.size;
+        let perm = get_random_permutation(n, rng);
+        let mut deltas = neighbourhood.init_deltas(instance, &perm);
+        let mut current = perm.clone();
+        for idx in 0..neighbourhood.size(n) {
+            let mv = neighbourhood.nth_move(n, idx);
+            let mut moved = perm.clone();
+            neighbourhood.apply(&mut moved, mv);
+            assert_ne!(perm, moved);
+            assert_eq!(
+                instance.evaluate_wide(&perm) - instance.evaluate_wide(&moved),
+                neighbourhood.delta(instance, &perm, mv)
+            );
+            neighbourhood.undo(&mut moved, mv);
+            assert_eq!(perm, moved);
+        }
+
+        for _ in 0..20 {
+            let mv = neighbourhood.random_move(n, rng).unwrap();
+            let expected = neighbourhood.delta(instance, &current, mv);
+            assert_eq!(expected, neighbourhood.cached_delta(&deltas, &current, mv));
+            neighbourhood.apply_cached(&mut deltas, &mut current, mv);
+        }
+    }
+
+    #[test]
+    fn moves_are_exact_and_reversible() {
+        let mut rng = StdRng::seed_from_u64(13);
+        let n = 7;
+        let mut random_matrix = || -> Vec<Vec<usize>> {
+            (0..n).map(|_| (0..n).map(|_| rng.gen_range(0..20)).collect()).collect()
+        };
+        let instance = Instance::new(random_matrix(), random_matrix(), 0, vec![]);
+
+        check_moves(Swap, &instance, &mut rng);
+        check_moves(ThreeCycle, &instance, &mut rng);
+        check_moves(Insertion, &instance, &mut rng);
+    }
+
+    #[test]
+    fn signed_and_fractional_deltas_are_exact() {
+        let mut rng = StdRng::seed_from_u64(14);
+        let n = 6;
+        let mut signed_matrix = || -> Vec<Vec<i64>> {
+            (0..n).map(|_| (0..n).map(|_| rng.gen_range(-20..20)).collect()).collect()
+        };
+        let signed = Instance::new(signed_matrix(), signed_matrix(), 0, vec![]);
+        // Halves keep every sum exact in binary floating point.
+        let fractional = Instance::new(
+            signed.matrix_a.rows().map(|row| row.iter().map(|&x| x as f64 / 2.0).collect()).collect(),
+            signed.matrix_b.rows().map(|row| row.iter().map(|&x| x as f64 / 2.0).collect()).collect(),
+            0.0,
+            vec![],
+        );
+
+        check_moves(Swap, &signed, &mut rng);
+        check_moves(ThreeCycle, &signed, &mut rng);
+        check_moves(Insertion, &fractional, &mut rng);
+        check_moves(Swap, &fractional, &mut rng);
+    }
+
+    #[test]
+    fn symmetric_deltas_are_exact() {
+        let mut rng = StdRng::seed_from_u64(15);
+        let n = 7;
+        let mut symmetric_matrix = || -> Vec<Vec<usize>> {
+            let upper: Vec<Vec<usize>> = (0..n).map(|_| (0..n).map(|_| rng.gen_range(0..20)).collect()).collect();
+            (0..n).map(|i| (0..n).map(|j| upper[i.min(j)][i.max(j)]).collect()).collect()
+        };
+        let instance = Instance::new(symmetric_matrix(), symmetric_matrix(), 0, vec![]);
+        assert!(instance.is_symmetric());
+
+        check_moves(Swap, &instance, &mut rng);
+        check_moves(ThreeCycle, &instance, &mut rng);
+        check_moves(Insertion, &instance, &mut rng);
+    }
+
+    #[test]
+    fn linear_and_lawler_deltas_are_exact() {
+        let mut rng = StdRng::seed_from_u64(16);
+        let n = 6;
+        let mut random_matrix = || -> Vec<Vec<usize>> {
+            (0..n).map(|_| (0..n).map(|_| rng.gen_range(0..20)).collect()).collect()
+        };
+        let linear = Instance::with_linear_costs(random_matrix(), random_matrix(), random_matrix(), 0, vec![]);
+        let lawler_costs = crate::matrix::LawlerCosts::from_flat(n, (0..n.pow(4)).map(|_| rng.gen_range(0..20)).collect());
+        let lawler = Instance::from_lawler_costs(lawler_costs, 0, vec![]);
+
+        for instance in [&linear, &lawler] {
+            check_moves(Swap, instance, &mut rng);
+            check_moves(ThreeCycle, instance, &mut rng);
+            check_moves(Insertion, instance, &mut rng);
+        }
+    }
+
+    #[test]
+    fn rectangular_moves_are_exact() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let (n, m) = (4, 7);
+        let mut random_matrix = |size: usize| -> Vec<Vec<usize>> {
+            (0..size).map(|_| (0..size).map(|_| rng.gen_range(0..20)).collect()).collect()
+        };
+        let (a, b) = (random_matrix(n), random_matrix(m));
+        let instance = Instance::new(a.clone(), b.clone(), 0, vec![]);
+        assert_eq!((n, m), (instance.get_facility_count(), instance.get_size()));
+
+        let perm = get_random_permutation(m, &mut rng);
+        let expected: usize = (0..n)
+            .flat_map(|i| (0..n).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * b[perm[i]][perm[j]])
+            .sum();
+        assert_eq!(expected, instance.evaluate(&perm).unwrap());
+
+        let relocation = Relocation::new(&instance);
+        let moves: Vec<(usize, usize)> = (0..relocation.size(m)).map(|idx| relocation.nth_move(m, idx)).collect();
+        assert_eq!(n * (m - n) + n * (n - 1) / 2, moves.len());
+        assert!(moves.iter().all(|&(i, j)| i < n && i != j));
+
+        check_moves(relocation, &instance, &mut rng);
+        check_moves(Swap, &instance, &mut rng);
+        check_moves(ThreeCycle, &instance, &mut rng);
+        check_moves(Insertion, &instance, &mut rng);
+    }
+
+    #[test]
+    fn small_neighbourhoods_are_empty() {
+        let mut rng = StdRng::seed_from_u64(18);
+        for n in 0..3 {
+            let matrix = vec![vec![1; n]; n];
+            let instance = Instance::new(matrix.clone(), matrix, 0, vec![]);
+            assert_eq!(n >= 2, Swap.random_move(n, &mut rng).is_some());
+            assert_eq!(n >= 2, Insertion.random_move(n, &mut rng).is_some());
+            assert_eq!(n >= 2, Relocation::new(&instance).random_move(n, &mut rng).is_some());
+            assert!(ThreeCycle.random_move(n, &mut rng).is_none());
+            assert!(ThreeCycle.random_feasible_move(&instance, &(0..n).collect::<Vec<_>>(), &mut rng).is_none());
+
+            let mut solver = SteepestSolver::with_neighbourhood(&instance, u128::MAX, ThreeCycle);
+            let solution = solver.solve((0..n).collect(), &mut rng).unwrap();
+            assert_eq!(0, solution.evaluations);
+            let mut solver = GreedySolver::with_neighbourhood(&instance, u128::MAX, ThreeCycle);
+            assert_eq!(0, solver.solve((0..n).collect(), &mut rng).unwrap().evaluations);
+            if n == 0 {
+                continue;
+            }
+            let mut solvers: Vec<Box<dyn Solver>> = vec![
+                Box::new(GreedySolver::new(&instance, u128::MAX)),
+                Box::new(GraspSolver::new(&instance, 1_000_000, GraspConfig::default())),
+                Box::new(MaxMinAntSystemSolver::new(&instance, 1_000_000, AntSystemConfig::default())),
+            ];
+            for solver in &mut solvers {
+                let solution = solver.solve((0..n).collect(), &mut rng).unwrap();
+                assert_eq!(n, solution.permutation.len(), "{}", solver.get_name());
+            }
+        }
+    }
+}
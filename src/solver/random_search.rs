@@ -1,45 +1,60 @@
-use crate::{get_random_permutation, instance::Instance};
+use rand::rngs::StdRng;
 
-use super::{Solution, Solver, SolvingError};
+use crate::{cost::Cost, instance::Instance};
 
-pub struct RandomSearchSolver<'a> {
-    instance: &'a Instance,
+use super::{
+    monitor::Monitor,
+    trace::Tracer,
+    Solution, Solver, SolvingError,
+};
+
+pub struct RandomSearchSolver<'a, C: Cost = usize> {
+    instance: &'a Instance<C>,
     max_iterations: usize,
     max_time: u128,
+    monitor: Monitor,
 }
 
-impl<'a> RandomSearchSolver<'a> {
-    pub fn new(instance: &Instance, max_iterations: usize, max_time: u128) -> RandomSearchSolver {
+impl<'a, C: Cost> RandomSearchSolver<'a, C> {
+    pub fn new(instance: &Instance<C>, max_iterations: usize, max_time: u128) -> RandomSearchSolver<'_, C> {
         RandomSearchSolver {
             instance,
             max_iterations,
             max_time,
+            monitor: Monitor::default(),
         }
     }
 }
 
-impl<'a> Solver for RandomSearchSolver<'a> {
-    fn solve(&mut self, _initial_solution: Vec<usize>) -> Result<Solution, SolvingError> {
-        let mut best_permutation = get_random_permutation(self.instance.size);
-        let mut best_cost = self.instance.evaluate(best_permutation.as_ref());
+impl<'a, C: Cost> Solver<C> for RandomSearchSolver<'a, C> {
+    fn solve(
+        &mut self,
+        _initial_solution: Vec<usize>,
+        rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        let mut best_permutation = self.instance.random_permutation(rng);
+        let mut best_cost = self.instance.evaluate(best_permutation.as_ref())?;
         let mut solution_changes = 0;
         let mut iteration = 0;
+        let mut tracer = Tracer::new(&self.monitor);
         let start = std::time::Instant::now();
 
-        while iteration < self.max_iterations && start.elapsed().as_nanos() < self.max_time {
-            let permutation = get_random_permutation(self.instance.size);
-            let cost = self.instance.evaluate(permutation.as_ref());
+        while iteration < self.max_iterations && start.elapsed().as_nanos() < self.max_time && !tracer.is_cancelled() {
+            let permutation = self.instance.random_permutation(rng);
+            let cost = self.instance.evaluate(permutation.as_ref())?;
             if cost < best_cost {
                 best_cost = cost;
                 best_permutation = permutation.clone();
                 solution_changes += 1;
             }
             iteration += 1;
+            tracer.record(iteration, cost.to_f64(), best_cost.to_f64());
         }
         Ok(Solution {
             permutation: best_permutation,
             evaluations: iteration,
             solution_changes,
+            trace: tracer.finish(iteration, best_cost.to_f64(), best_cost.to_f64()),
         })
     }
 
@@ -47,7 +62,7 @@ impl<'a> Solver for RandomSearchSolver<'a> {
         "RandomSearchSolver".to_string()
     }
 
-    fn get_instance(&self) -> &Instance {
+    fn get_instance(&self) -> &Instance<C> {
         self.instance
     }
 
@@ -58,4 +73,8 @@ impl<'a> Solver for RandomSearchSolver<'a> {
     fn get_time_limit(&self) -> u128 {
         self.max_time
     }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
 }
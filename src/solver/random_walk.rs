@@ -1,58 +1,92 @@
-use rand::random;
+use rand::{rngs::StdRng, Rng};
 
-use crate::instance::Instance;
+use crate::{cost::Cost, instance::Instance};
 
-use super::{compute_num_neighbours, eval_diff, move_to_neighbour, Solution, Solver, SolvingError};
+use super::{
+    neighbourhood::{solver_name, Neighbourhood, Swap},
+    monitor::Monitor,
+    trace::Tracer,
+    Solution, Solver, SolvingError,
+};
 
-pub struct RandomWalkSolver<'a> {
-    instance: &'a Instance,
+pub struct RandomWalkSolver<'a, C: Cost = usize, N: Neighbourhood = Swap> {
+    instance: &'a Instance<C>,
     max_iterations: usize,
     max_time: u128,
+    monitor: Monitor,
     best_solution: Vec<usize>,
+    neighbourhood: N,
 }
 
-impl<'a> RandomWalkSolver<'a> {
-    pub fn new(instance: &Instance, max_iterations: usize, max_time: u128) -> RandomWalkSolver {
+impl<'a, C: Cost> RandomWalkSolver<'a, C> {
+    pub fn new(instance: &Instance<C>, max_iterations: usize, max_time: u128) -> RandomWalkSolver<'_, C> {
+        RandomWalkSolver::with_neighbourhood(instance, max_iterations, max_time, Swap)
+    }
+}
+
+impl<'a, C: Cost, N: Neighbourhood> RandomWalkSolver<'a, C, N> {
+    pub fn with_neighbourhood(
+        instance: &Instance<C>,
+        max_iterations: usize,
+        max_time: u128,
+        neighbourhood: N,
+    ) -> RandomWalkSolver<'_, C, N> {
         RandomWalkSolver {
             instance,
             max_iterations,
             max_time,
+            monitor: Monitor::default(),
             best_solution: Vec::with_capacity(instance.size),
+            neighbourhood,
         }
     }
 }
 
-impl<'a> Solver for RandomWalkSolver<'a> {
-    fn solve(&mut self, mut initial_solution: Vec<usize>) -> Result<Solution, SolvingError> {
-        let num_neighbours = compute_num_neighbours(self.instance.size);
+impl<'a, C: Cost, N: Neighbourhood> Solver<C> for RandomWalkSolver<'a, C, N> {
+    fn solve(
+        &mut self,
+        mut initial_solution: Vec<usize>,
+        rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        self.instance.get_constraints().check(&initial_solution)?;
         let mut evaluations = 0;
         let mut solution_changes = 0;
         let mut iteration = 0;
+        let mut current_cost = self.instance.evaluate_wide(&initial_solution);
+        let mut best_cost = current_cost;
+        self.best_solution = initial_solution.clone();
+        let mut tracer = Tracer::new(&self.monitor);
         let start = std::time::Instant::now();
 
-        while iteration < self.max_iterations && start.elapsed().as_nanos() < self.max_time {
-            let random_neighbour_idx = random::<usize>() % num_neighbours;
-            let diff = eval_diff(self.instance, &initial_solution, random_neighbour_idx);
-            initial_solution = move_to_neighbour(initial_solution, random_neighbour_idx);
+        while iteration < self.max_iterations && start.elapsed().as_nanos() < self.max_time && !tracer.is_cancelled() {
+            let Some(mv) = self.neighbourhood.random_feasible_move(self.instance, &initial_solution, rng) else {
+                break;
+            };
+            let diff = self.neighbourhood.delta(self.instance, &initial_solution, mv);
+            self.neighbourhood.apply(&mut initial_solution, mv);
             evaluations += 1;
+            current_cost -= diff;
 
-            if diff > 0 {
+            if current_cost < best_cost {
                 self.best_solution = initial_solution.clone();
+                best_cost = current_cost;
                 solution_changes += 1;
             }
             iteration += 1;
+            tracer.record(evaluations, C::wide_to_f64(current_cost), C::wide_to_f64(best_cost));
         }
         Ok(Solution {
             permutation: self.best_solution.clone(),
             evaluations,
             solution_changes,
+            trace: tracer.finish(evaluations, C::wide_to_f64(current_cost), C::wide_to_f64(best_cost)),
         })
     }
     fn get_name(&self) -> String {
-        "RandomWalkSolver".to_string()
+        solver_name("RandomWalkSolver", &self.neighbourhood)
     }
 
-    fn get_instance(&self) -> &Instance {
+    fn get_instance(&self) -> &Instance<C> {
         self.instance
     }
 
@@ -63,22 +97,67 @@ impl<'a> Solver for RandomWalkSolver<'a> {
     fn get_time_limit(&self) -> u128 {
         self.max_time
     }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
 }
 
-pub fn random_walk(
-    instance: &Instance,
+pub fn random_walk<C: Cost, N: Neighbourhood, R: Rng + ?Sized>(
+    instance: &Instance<C>,
+    neighbourhood: &N,
     mut initial_solution: Vec<usize>,
     max_iterations: usize,
+    rng: &mut R,
 ) -> Vec<usize> {
     let mut best_solution = initial_solution.clone();
+    // Costs relative to the initial solution.
+    let mut current_cost = C::Wide::default();
+    let mut best_cost = current_cost;
     for _ in 0..max_iterations {
-        let random_neighbour_idx = random::<usize>() % instance.size;
-        let diff = eval_diff(instance, &best_solution, random_neighbour_idx);
-        initial_solution = move_to_neighbour(initial_solution, random_neighbour_idx);
+        let Some(mv) = neighbourhood.random_feasible_move(instance, &initial_solution, rng) else {
+            break;
+        };
+        let diff = neighbourhood.delta(instance, &initial_solution, mv);
+        neighbourhood.apply(&mut initial_solution, mv);
+        current_cost -= diff;
 
-        if diff > 0 {
+        if current_cost < best_cost {
             best_solution = initial_solution.clone();
+            best_cost = current_cost;
         }
     }
     best_solution
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{
+        generator::{li_pardalos, LipaKind},
+        solver::trace::Tracing,
+    };
+
+    #[test]
+    fn returns_the_best_solution_visited() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let single = Instance::new(vec![vec![2]], vec![vec![5]], 10, vec![0]);
+        let solution = RandomWalkSolver::new(&single, 100, u128::MAX).solve(vec![0], &mut rng).unwrap();
+        assert_eq!(vec![0], solution.permutation);
+
+        let instance = li_pardalos(10, LipaKind::A, 2);
+        let mut solver = RandomWalkSolver::new(&instance, 5_000, u128::MAX);
+        solver.set_monitor(Monitor::new().with_tracing(Tracing::Improvements));
+        let start = instance.random_permutation(&mut rng);
+        let start_cost = instance.evaluate(&start).unwrap() as f64;
+        let solution = solver.solve(start, &mut rng).unwrap();
+
+        let best = instance.evaluate(&solution.permutation).unwrap() as f64;
+        assert_eq!(solution.trace.last().unwrap().best_cost, best);
+        assert!(best <= start_cost);
+        assert!(solution.trace.windows(2).all(|pair| pair[1].best_cost <= pair[0].best_cost));
+        assert!(solution.trace.iter().all(|point| point.best_cost <= point.current_cost));
+    }
+}
@@ -1,39 +1,118 @@
-use std::usize;
+use rand::{rngs::StdRng, Rng};
+use serde::Deserialize;
 
-use rand::random;
-
-use super::compute_num_neighbours;
-use super::eval_diff;
-use super::move_to_neighbour;
+use super::neighbourhood::{solver_name, Neighbourhood, Swap};
+use super::monitor::Monitor;
+use super::trace::Tracer;
 use super::Solution;
 use super::SolvingError;
-use crate::get_random_permutation;
+use crate::cost::Cost;
 use crate::instance::Instance;
 
+use cooling::{CoolingSchedule, CoolingState, Geometric};
+
+pub mod cooling;
+
 const NUM_INITIAL_TEMPERATURE_SAMPLES: usize = 100;
-const MAX_NO_IMPROVEMENT_ITERAIONS: usize = 200_000;
 
-pub struct SimulatedAnnealingSolver<'a> {
-    instance: &'a Instance,
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnnealingConfig {
+    /// Moves tried at every temperature.
+    pub epoch_length: usize,
+    /// Probability of accepting an average worsening move at the initial temperature,
+    /// strictly between 0 and 1.
+    pub initial_acceptance: f64,
+    /// Stop after this many moves without improving the best solution.
+    pub max_no_improvement: usize,
+    /// Stop once the temperature drops to this fraction of the initial temperature.
+    pub min_temperature_ratio: f64,
+}
+
+impl Default for AnnealingConfig {
+    fn default() -> Self {
+        AnnealingConfig {
+            epoch_length: 1000,
+            initial_acceptance: 0.9,
+            max_no_improvement: 200_000,
+            min_temperature_ratio: 1e-3,
+        }
+    }
+}
+
+pub struct SimulatedAnnealingSolver<'a, C: Cost = usize, N: Neighbourhood = Swap> {
+    instance: &'a Instance<C>,
     max_time: u128,
+    monitor: Monitor,
+    config: AnnealingConfig,
+    schedule: Box<dyn CoolingSchedule>,
+    neighbourhood: N,
 }
 
-impl<'a> SimulatedAnnealingSolver<'a> {
-    pub fn new(instance: &Instance, max_time: u128) -> SimulatedAnnealingSolver {
-        SimulatedAnnealingSolver { instance, max_time }
+impl<'a, C: Cost> SimulatedAnnealingSolver<'a, C> {
+    pub fn new(instance: &Instance<C>, max_time: u128) -> SimulatedAnnealingSolver<'_, C> {
+        Self::with_schedule(
+            instance,
+            max_time,
+            AnnealingConfig::default(),
+            Box::new(Geometric { alpha: 0.95 }),
+        )
+    }
+
+    pub fn with_schedule(
+        instance: &Instance<C>,
+        max_time: u128,
+        config: AnnealingConfig,
+        schedule: Box<dyn CoolingSchedule>,
+    ) -> SimulatedAnnealingSolver<'_, C> {
+        SimulatedAnnealingSolver::with_neighbourhood(instance, max_time, config, schedule, Swap)
     }
 }
 
-impl<'a> super::Solver for SimulatedAnnealingSolver<'a> {
-    fn solve(&mut self, starting_perm: Vec<usize>) -> Result<Solution, SolvingError> {
-        Ok(simulated_annealing(self.instance, starting_perm))
+impl<'a, C: Cost, N: Neighbourhood> SimulatedAnnealingSolver<'a, C, N> {
+    pub fn with_neighbourhood(
+        instance: &Instance<C>,
+        max_time: u128,
+        config: AnnealingConfig,
+        schedule: Box<dyn CoolingSchedule>,
+        neighbourhood: N,
+    ) -> SimulatedAnnealingSolver<'_, C, N> {
+        SimulatedAnnealingSolver {
+            instance,
+            max_time,
+            monitor: Monitor::default(),
+            config,
+            schedule,
+            neighbourhood,
+        }
+    }
+}
+
+impl<'a, C: Cost, N: Neighbourhood> super::Solver<C> for SimulatedAnnealingSolver<'a, C, N> {
+    fn solve(
+        &mut self,
+        starting_perm: Vec<usize>,
+        rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        self.instance.get_constraints().check(&starting_perm)?;
+        self.schedule.reset();
+        simulated_annealing(
+            self.instance,
+            &self.neighbourhood,
+            starting_perm,
+            &self.config,
+            &mut *self.schedule,
+            self.max_time,
+            &self.monitor,
+            rng,
+        )
     }
 
     fn get_name(&self) -> String {
-        "Simulated Annealing".to_string()
+        solver_name("Simulated Annealing", &self.neighbourhood)
     }
 
-    fn get_instance(&self) -> &Instance {
+    fn get_instance(&self) -> &Instance<C> {
         self.instance
     }
 
@@ -44,72 +123,188 @@ impl<'a> super::Solver for SimulatedAnnealingSolver<'a> {
     fn get_time_limit(&self) -> u128 {
         self.max_time
     }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
 }
 
-pub fn simulated_annealing(instance: &Instance, mut starting_solution: Vec<usize>) -> Solution {
-    let mut temperature = set_initial_temperatrue(instance);
-    let neighbours_num = compute_num_neighbours(instance.get_size());
-    let iter_per_temperature = 1000;
+#[allow(clippy::too_many_arguments)]
+pub fn simulated_annealing<C: Cost, N: Neighbourhood, R: Rng + ?Sized>(
+    instance: &Instance<C>,
+    neighbourhood: &N,
+    mut current_solution: Vec<usize>,
+    config: &AnnealingConfig,
+    schedule: &mut dyn CoolingSchedule,
+    max_time: u128,
+    monitor: &Monitor,
+    rng: &mut R,
+) -> Result<Solution, SolvingError> {
+    if !(config.initial_acceptance > 0.0 && config.initial_acceptance < 1.0) {
+        return Err(SolvingError {
+            message: format!("Initial acceptance {} should be between 0 and 1", config.initial_acceptance),
+        });
+    }
+    let start = std::time::Instant::now();
+    let mut tracer = Tracer::new(monitor);
+    let initial_temperature = set_initial_temperatrue(instance, neighbourhood, config.initial_acceptance, rng);
+    let min_temperature = config.min_temperature_ratio * initial_temperature;
+    let mut temperature = initial_temperature;
+
+    let mut current_cost = instance.evaluate_wide(&current_solution);
+    let mut best_solution = current_solution.clone();
+    let mut best_cost = current_cost;
+    let mut evaluations = NUM_INITIAL_TEMPERATURE_SAMPLES;
+    let mut solution_changes = 0;
     let mut no_improvement_iterations = 0;
+    let mut epoch = 0;
+    let mut epochs_without_improvement = 0;
 
-    while no_improvement_iterations < MAX_NO_IMPROVEMENT_ITERAIONS {
-        for _ in 0..iter_per_temperature {
-            let neighbour_idx = generate_random_neighbour(neighbours_num);
-            let delta = eval_diff(instance, &starting_solution, neighbour_idx);
+    'annealing: while temperature > min_temperature {
+        let mut improved_in_epoch = false;
+        for _ in 0..config.epoch_length {
+            if no_improvement_iterations >= config.max_no_improvement
+                || start.elapsed().as_nanos() > max_time
+                || tracer.is_cancelled()
+            {
+                break 'annealing;
+            }
 
-            if delta > 0 {
-                starting_solution = move_to_neighbour(starting_solution, neighbour_idx);
+            let Some(mv) = neighbourhood.random_feasible_move(instance, &current_solution, rng) else {
+                break 'annealing;
+            };
+            let delta = neighbourhood.delta(instance, &current_solution, mv);
+            evaluations += 1;
+
+            if delta > C::Wide::default()
+                || calculate_probability(C::wide_to_f64(delta), temperature) > generate_random_number(rng)
+            {
+                neighbourhood.apply(&mut current_solution, mv);
+                current_cost -= delta;
+                solution_changes += 1;
+            }
+
+            if current_cost < best_cost {
+                best_cost = current_cost;
+                best_solution.clone_from(&current_solution);
                 no_improvement_iterations = 0;
+                improved_in_epoch = true;
             } else {
                 no_improvement_iterations += 1;
-                let probability = calculate_probability(delta as f64, temperature);
-                if probability > generate_random_number() {
-                    starting_solution = move_to_neighbour(starting_solution, neighbour_idx);
-                }
             }
+            tracer.record(evaluations, C::wide_to_f64(current_cost), C::wide_to_f64(best_cost));
         }
 
-        temperature = update_temperature(temperature);
+        epoch += 1;
+        epochs_without_improvement = if improved_in_epoch { 0 } else { epochs_without_improvement + 1 };
+        temperature = schedule.next_temperature(&CoolingState {
+            initial_temperature,
+            temperature,
+            epoch,
+            epochs_without_improvement,
+        });
     }
 
-    Solution {
-        permutation: starting_solution,
-        evaluations: 0,
-        solution_changes: 0,
-    }
+    Ok(Solution {
+        permutation: best_solution,
+        evaluations,
+        solution_changes,
+        trace: tracer.finish(evaluations, C::wide_to_f64(current_cost), C::wide_to_f64(best_cost)),
+    })
 }
 
-fn set_initial_temperatrue(instance: &Instance) -> f64 {
+fn set_initial_temperatrue<C: Cost, N: Neighbourhood, R: Rng + ?Sized>(
+    instance: &Instance<C>,
+    neighbourhood: &N,
+    acceptance: f64,
+    rng: &mut R,
+) -> f64 {
     // "
     //     uniformly sample solution space
     //     calculate the average delta
-    //     solve e^(-avg_delta / temperature) = 0.9
+    //     solve e^(-avg_delta / temperature) = acceptance
     // "
 
     let mut total_delta = 0.0;
     for _ in 0..NUM_INITIAL_TEMPERATURE_SAMPLES {
-        let perm = get_random_permutation(instance.get_size());
-        let idx = random::<usize>() % compute_num_neighbours(instance.get_size());
-        let delta = eval_diff(instance, &perm, idx).abs();
-        total_delta += delta as f64;
+        let perm = instance.random_permutation(rng);
+        if let Some(mv) = neighbourhood.random_feasible_move(instance, &perm, rng) {
+            total_delta += C::wide_to_f64(neighbourhood.delta(instance, &perm, mv)).abs();
+        }
     }
 
     let avg_delta = total_delta / NUM_INITIAL_TEMPERATURE_SAMPLES as f64;
-    -avg_delta / (0.9f64.ln())
-}
-
-fn generate_random_neighbour(num_neighbours: usize) -> usize {
-    rand::random::<usize>() % num_neighbours
+    if avg_delta == 0.0 {
+        return 1.0;
+    }
+    -avg_delta / acceptance.ln()
 }
 
 fn calculate_probability(delta: f64, temperature: f64) -> f64 {
     (delta / temperature).exp()
 }
 
-fn generate_random_number() -> f64 {
-    rand::random::<f64>()
+fn generate_random_number<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    rng.gen::<f64>()
 }
 
-fn update_temperature(temperature: f64) -> f64 {
-    temperature * 0.95
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{
+        generator::{li_pardalos, LipaKind},
+        solver::{trace::Tracing, Solver},
+    };
+    use cooling::Linear;
+
+    #[test]
+    fn linear_cooling_ends_the_run() {
+        let instance = li_pardalos(8, LipaKind::A, 1);
+        let config = AnnealingConfig {
+            epoch_length: 10,
+            max_no_improvement: usize::MAX,
+            ..AnnealingConfig::default()
+        };
+        let mut solver = SimulatedAnnealingSolver::with_schedule(&instance, u128::MAX, config, Box::new(Linear { epochs: 50 }));
+        let mut rng = StdRng::seed_from_u64(4);
+
+        let solution = solver.solve(instance.random_permutation(&mut rng), &mut rng).unwrap();
+
+        assert!(solution.evaluations <= NUM_INITIAL_TEMPERATURE_SAMPLES + 50 * 10);
+    }
+
+    #[test]
+    fn rejects_acceptance_outside_the_unit_interval() {
+        let instance = li_pardalos(5, LipaKind::A, 1);
+        let mut rng = StdRng::seed_from_u64(5);
+        for initial_acceptance in [0.0, 1.0, 1.5, -0.2] {
+            let config = AnnealingConfig { initial_acceptance, ..AnnealingConfig::default() };
+            let mut solver = SimulatedAnnealingSolver::with_schedule(&instance, u128::MAX, config, Box::new(Geometric { alpha: 0.9 }));
+            assert!(solver.solve(instance.random_permutation(&mut rng), &mut rng).is_err());
+        }
+    }
+
+    #[test]
+    fn returns_the_best_solution_seen() {
+        let instance = li_pardalos(12, LipaKind::B, 2);
+        // Never cools down, so the search keeps wandering away from its best solution.
+        let config = AnnealingConfig {
+            initial_acceptance: 0.99,
+            max_no_improvement: 2_000,
+            ..AnnealingConfig::default()
+        };
+        let mut solver = SimulatedAnnealingSolver::with_schedule(&instance, u128::MAX, config, Box::new(Geometric { alpha: 1.0 }));
+        solver.set_monitor(Monitor::new().with_tracing(Tracing::Interval(1)));
+        let mut rng = StdRng::seed_from_u64(6);
+
+        let solution = solver.solve(instance.random_permutation(&mut rng), &mut rng).unwrap();
+
+        let cost = instance.evaluate(&solution.permutation).unwrap() as f64;
+        let last = solution.trace.last().unwrap();
+        assert!(last.current_cost > cost);
+        assert_eq!(cost, last.best_cost);
+        assert!(solution.trace.iter().all(|point| point.current_cost >= cost));
+    }
 }
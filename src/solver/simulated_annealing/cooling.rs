@@ -0,0 +1,171 @@
+/// What a cooling schedule may look at when picking the temperature of the next epoch.
+pub struct CoolingState {
+    pub initial_temperature: f64,
+    pub temperature: f64,
+    /// Number of finished epochs, starting at 1.
+    pub epoch: usize,
+    /// Finished epochs in a row that did not improve the best solution.
+    pub epochs_without_improvement: usize,
+}
+
+pub trait CoolingSchedule {
+    fn next_temperature(&mut self, state: &CoolingState) -> f64;
+    fn get_name(&self) -> String;
+
+    /// Forgets any state kept between epochs, called at the start of every run.
+    fn reset(&mut self) {}
+}
+
+/// `T' = alpha * T`.
+pub struct Geometric {
+    pub alpha: f64,
+}
+
+impl CoolingSchedule for Geometric {
+    fn next_temperature(&mut self, state: &CoolingState) -> f64 {
+        state.temperature * self.alpha
+    }
+
+    fn get_name(&self) -> String {
+        format!("Geometric({})", self.alpha)
+    }
+}
+
+/// `T_k = T_0 - k * T_0 / epochs`, reaching zero after `epochs` epochs.
+pub struct Linear {
+    pub epochs: usize,
+}
+
+impl CoolingSchedule for Linear {
+    fn next_temperature(&mut self, state: &CoolingState) -> f64 {
+        let step = state.initial_temperature / self.epochs as f64;
+        (state.temperature - step).max(0.0)
+    }
+
+    fn get_name(&self) -> String {
+        format!("Linear({})", self.epochs)
+    }
+}
+
+/// Lundy and Mees: `T' = T / (1 + beta * T)`.
+pub struct LundyMees {
+    pub beta: f64,
+}
+
+impl CoolingSchedule for LundyMees {
+    fn next_temperature(&mut self, state: &CoolingState) -> f64 {
+        state.temperature / (1.0 + self.beta * state.temperature)
+    }
+
+    fn get_name(&self) -> String {
+        format!("LundyMees({})", self.beta)
+    }
+}
+
+/// `T_k = T_0 * ln 2 / ln(k + 2)`, the slow schedule from the convergence proofs.
+pub struct Logarithmic;
+
+impl CoolingSchedule for Logarithmic {
+    fn next_temperature(&mut self, state: &CoolingState) -> f64 {
+        state.initial_temperature * 2f64.ln() / (state.epoch as f64 + 2.0).ln()
+    }
+
+    fn get_name(&self) -> String {
+        "Logarithmic".to_string()
+    }
+}
+
+/// Geometric cooling that reheats to `reheat_ratio * T_0` once the best solution has not
+/// improved for `patience` epochs.
+pub struct Reheating {
+    pub alpha: f64,
+    pub patience: usize,
+    pub reheat_ratio: f64,
+    last_reheat_epoch: usize,
+}
+
+impl Reheating {
+    pub fn new(alpha: f64, patience: usize, reheat_ratio: f64) -> Reheating {
+        Reheating {
+            alpha,
+            patience,
+            reheat_ratio,
+            last_reheat_epoch: 0,
+        }
+    }
+}
+
+impl CoolingSchedule for Reheating {
+    fn next_temperature(&mut self, state: &CoolingState) -> f64 {
+        let stalled = state.epochs_without_improvement >= self.patience
+            && state.epoch - self.last_reheat_epoch >= self.patience;
+        if stalled {
+            self.last_reheat_epoch = state.epoch;
+            state.initial_temperature * self.reheat_ratio
+        } else {
+            state.temperature * self.alpha
+        }
+    }
+
+    fn get_name(&self) -> String {
+        format!("Reheating({}, {}, {})", self.alpha, self.patience, self.reheat_ratio)
+    }
+
+    fn reset(&mut self) {
+        self.last_reheat_epoch = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Temperatures of the first `epochs` epochs from `initial_temperature`, where the best
+    /// solution improves only in the epochs listed in `improving`.
+    fn temperatures(schedule: &mut dyn CoolingSchedule, initial_temperature: f64, epochs: usize, improving: &[usize]) -> Vec<f64> {
+        let mut temperature = initial_temperature;
+        let mut epochs_without_improvement = 0;
+        (1..=epochs)
+            .map(|epoch| {
+                epochs_without_improvement = if improving.contains(&epoch) { 0 } else { epochs_without_improvement + 1 };
+                temperature = schedule.next_temperature(&CoolingState {
+                    initial_temperature,
+                    temperature,
+                    epoch,
+                    epochs_without_improvement,
+                });
+                temperature
+            })
+            .collect()
+    }
+
+    fn assert_close(expected: &[f64], actual: &[f64]) {
+        assert_eq!(expected.len(), actual.len());
+        for (expected, actual) in expected.iter().zip(actual) {
+            assert!((expected - actual).abs() < 1e-12, "{:?} != {:?}", expected, actual);
+        }
+    }
+
+    #[test]
+    fn schedules_follow_their_formulas() {
+        assert_close(&[4.0, 2.0, 1.0], &temperatures(&mut Geometric { alpha: 0.5 }, 8.0, 3, &[]));
+        assert_close(&[6.0, 4.0, 2.0, 0.0, 0.0], &temperatures(&mut Linear { epochs: 4 }, 8.0, 5, &[]));
+        assert_close(&[0.5, 1.0 / 3.0, 0.25], &temperatures(&mut LundyMees { beta: 1.0 }, 1.0, 3, &[]));
+        assert_close(
+            &[6.0 * 2f64.ln() / 3f64.ln(), 3.0, 6.0 * 2f64.ln() / 5f64.ln()],
+            &temperatures(&mut Logarithmic, 6.0, 3, &[]),
+        );
+    }
+
+    #[test]
+    fn reheating_waits_for_its_patience() {
+        let mut schedule = Reheating::new(0.5, 2, 0.5);
+        assert_close(
+            &[4.0, 2.0, 1.0, 0.5, 4.0, 2.0, 4.0],
+            &temperatures(&mut schedule, 8.0, 7, &[1, 3]),
+        );
+
+        schedule.reset();
+        assert_close(&[4.0, 4.0], &temperatures(&mut schedule, 8.0, 2, &[]));
+    }
+}
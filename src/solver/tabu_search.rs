@@ -1,133 +1,270 @@
-use std::collections::BinaryHeap;
+use rand::{rngs::StdRng, Rng};
 
-use crate::instance;
+use crate::{cost::Cost, instance::Instance};
 
-use super::{calculate_swap_indices, eval_diff, move_to_neighbour};
+use super::{
+    delta_matrix::DeltaMatrix,
+    neighbourhood::{Neighbourhood, Swap},
+    monitor::Monitor,
+    trace::Tracer,
+    Solution, Solver, SolvingError,
+};
 
-const MAX_ITERATIONS_WITHOUT_IMPROVEMENT: usize = 100;
-const CANDIDATE_LIST_SIZE: usize = 10;
-const TABU_TENURE: usize = 5;
+// Taillard's robust tabu search draws the tenure uniformly from [0.9n, 1.1n].
+const MIN_TENURE_RATIO: f64 = 0.9;
+const MAX_TENURE_RATIO: f64 = 1.1;
 
-pub struct TabuSearchAlgorithm<'a> {
-    instance: &'a instance::Instance,
-    current_solution: Vec<usize>,
-    best_solution: Vec<usize>,
-    current_cost: usize,
-    best_cost: usize,
-    tabu_list: Vec<Vec<usize>>,
-    iterations_without_improvement: usize,
-    iteration: usize,
-    candidate_threshold_cost: usize,
-    candidate_list: BinaryHeap<(usize, isize)>,
+/// Robust Tabu Search (Taillard, 1991) over the swap neighbourhood.
+///
+/// A swap of positions `i` and `j` is tabu when it would put both facilities back on
+/// locations they left within their tenure. Tabu moves are still taken when they lead to a
+/// new best solution (aspiration by best cost).
+pub struct TabuSearchSolver<'a, C: Cost = usize> {
+    instance: &'a Instance<C>,
+    max_evaluations: usize,
+    max_time: u128,
+    monitor: Monitor,
 }
 
-impl<'a> TabuSearchAlgorithm<'a> {
-    pub fn new(instance: &instance::Instance, initial_solution: Vec<usize>) -> TabuSearchAlgorithm {
-        let n = instance.size;
-        let tabu_list = vec![vec![0; n]; n];
-        let candidate_list = BinaryHeap::with_capacity(CANDIDATE_LIST_SIZE);
-        let candidate_threshold_cost = 0;
-        let iterations_without_improvement = 0;
-        let iteration = 0;
-        let current_cost = instance.evaluate(&initial_solution);
-        let best_solution = initial_solution.clone();
-
-        TabuSearchAlgorithm {
+impl<'a, C: Cost> TabuSearchSolver<'a, C> {
+    pub fn new(instance: &Instance<C>, max_evaluations: usize, max_time: u128) -> TabuSearchSolver<'_, C> {
+        TabuSearchSolver {
             instance,
-            current_solution: initial_solution,
-            best_solution,
-            current_cost,
-            best_cost: current_cost,
-            tabu_list,
-            iterations_without_improvement,
-            iteration,
-            candidate_threshold_cost,
-            candidate_list,
+            max_evaluations,
+            max_time,
+            monitor: Monitor::default(),
         }
     }
 
-    pub fn solve(mut self) -> Vec<usize> {
-        self.generate_candidate_list();
-
-        while self.iterations_without_improvement < MAX_ITERATIONS_WITHOUT_IMPROVEMENT {
-            let (neighbour_idx, cost_diff) = self.choose_from_candidate_list();
+    fn draw_tenure(&self, rng: &mut StdRng) -> usize {
+        let n = self.instance.size as f64;
+        let min_tenure = (MIN_TENURE_RATIO * n).floor() as usize;
+        let max_tenure = (MAX_TENURE_RATIO * n).ceil() as usize;
+        rng.gen_range(min_tenure..=max_tenure.max(min_tenure + 1))
+    }
 
-            // todo!("below 2 instructions should be in one method");
-            self.current_solution = move_to_neighbour(self.current_solution, neighbour_idx);
-            self.current_cost += cost_diff as usize;
+    /// The best swap of `perm` that is not tabu or leads to a new best solution, or the best
+    /// one when every swap is tabu; `None` when no swap is feasible.
+    fn select_move(
+        &self,
+        deltas: &DeltaMatrix<C>,
+        perm: &[usize],
+        tabu_list: &TabuList,
+        (current_cost, best_cost): (C::Wide, C::Wide),
+        evaluations: &mut usize,
+    ) -> Option<(usize, usize, C::Wide)> {
+        let mut chosen: Option<(usize, usize, C::Wide)> = None;
+        let mut fallback: Option<(usize, usize, C::Wide)> = None;
 
-            self.update_tabu_list(neighbour_idx);
+        for i in 0..perm.len() {
+            for j in i + 1..perm.len() {
+                if !Swap.is_feasible(self.instance, perm, (i, j)) {
+                    continue;
+                }
+                let diff = deltas.get(i, j);
+                *evaluations += 1;
 
-            if self.current_cost < self.best_cost {
-                self.best_solution = self.current_solution.clone();
-                self.best_cost = self.current_cost;
-                self.iterations_without_improvement = 0;
-            } else {
-                self.iterations_without_improvement += 1;
+                let aspirated = current_cost - diff < best_cost;
+                if (!tabu_list.is_tabu(perm, i, j) || aspirated) && chosen.is_none_or(|(_, _, best)| diff > best) {
+                    chosen = Some((i, j, diff));
+                }
+                if fallback.is_none_or(|(_, _, best)| diff > best) {
+                    fallback = Some((i, j, diff));
+                }
             }
-
-            self.iteration += 1;
         }
-        self.best_solution
+        // Every move is tabu: take the least bad one instead of stalling.
+        chosen.or(fallback)
     }
+}
 
-    fn generate_candidate_list(&mut self) {
-        let num_neighbours = self.instance.size * (self.instance.size - 1) / 2;
-        let mut worst_candidate_cost;
+/// `until[facility][location]`: first iteration at which the facility may return to the
+/// location.
+struct TabuList {
+    until: Vec<Vec<usize>>,
+    iteration: usize,
+}
 
-        for neighbour_idx in 0..CANDIDATE_LIST_SIZE {
-            let diff = eval_diff(self.instance, &self.current_solution, neighbour_idx);
-            self.candidate_list.push((neighbour_idx, diff as isize));
+impl TabuList {
+    fn new(n: usize) -> TabuList {
+        TabuList {
+            until: vec![vec![0; n]; n],
+            iteration: 0,
         }
+    }
+
+    /// Whether swapping positions `i` and `j` would put both facilities back on locations
+    /// they left within their tenure.
+    fn is_tabu(&self, perm: &[usize], i: usize, j: usize) -> bool {
+        self.until[i][perm[j]] > self.iteration && self.until[j][perm[i]] > self.iteration
+    }
 
-        worst_candidate_cost = self
-            .candidate_list
-            .peek()
-            .expect("Candidate list should not be empty")
-            .1;
-
-        for neighbour_idx in CANDIDATE_LIST_SIZE..num_neighbours {
-            let diff = eval_diff(self.instance, &self.current_solution, neighbour_idx) as isize;
-            if diff < worst_candidate_cost {
-                self.candidate_list.pop();
-                self.candidate_list.push((neighbour_idx, diff));
-                worst_candidate_cost = self
-                    .candidate_list
-                    .peek()
-                    .expect("Candidate list should not be empty")
-                    .1;
+    /// Forbids `facility` to return to `location` for `tenure` iterations.
+    fn forbid(&mut self, facility: usize, location: usize, tenure: usize) {
+        self.until[facility][location] = self.iteration + tenure;
+    }
+}
+
+impl<'a, C: Cost> Solver<C> for TabuSearchSolver<'a, C> {
+    fn solve(
+        &mut self,
+        mut current_solution: Vec<usize>,
+        rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        self.instance.get_constraints().check(&current_solution)?;
+        let n = current_solution.len();
+        let mut deltas = DeltaMatrix::new(self.instance, &current_solution);
+        let mut tabu_list = TabuList::new(n);
+        let mut current_cost = self.instance.evaluate_wide(&current_solution);
+        let mut best_solution = current_solution.clone();
+        let mut best_cost = current_cost;
+
+        let mut evaluations = 0;
+        let mut solution_changes = 0;
+        let mut tracer = Tracer::new(&self.monitor);
+        tracer.record(0, C::wide_to_f64(current_cost), C::wide_to_f64(best_cost));
+        let start = std::time::Instant::now();
+
+        while evaluations < self.max_evaluations && start.elapsed().as_nanos() < self.max_time && !tracer.is_cancelled() {
+            let costs = (current_cost, best_cost);
+            let Some((i, j, diff)) = self.select_move(&deltas, &current_solution, &tabu_list, costs, &mut evaluations)
+            else {
+                break;
+            };
+
+            tabu_list.forbid(i, current_solution[i], self.draw_tenure(rng));
+            tabu_list.forbid(j, current_solution[j], self.draw_tenure(rng));
+            deltas.apply_swap(&mut current_solution, i, j);
+            current_cost -= diff;
+            solution_changes += 1;
+
+            if current_cost < best_cost {
+                best_cost = current_cost;
+                best_solution.clone_from(&current_solution);
             }
+            tabu_list.iteration += 1;
+            tracer.record(evaluations, C::wide_to_f64(current_cost), C::wide_to_f64(best_cost));
         }
+
+        Ok(Solution {
+            permutation: best_solution,
+            evaluations,
+            solution_changes,
+            trace: tracer.finish(evaluations, C::wide_to_f64(current_cost), C::wide_to_f64(best_cost)),
+        })
     }
 
-    fn choose_from_candidate_list(&mut self) -> (usize, isize) {
-        let (worst_candidate_idx, worst_candidate_cost) = self
-            .candidate_list
-            .peek()
-            .expect("Candidate list should not be empty");
+    fn get_name(&self) -> String {
+        "TabuSearchSolver".to_string()
+    }
 
-        let mut best_candidate_cost = *worst_candidate_cost;
-        let mut best_candidate_idx = *worst_candidate_idx;
+    fn get_instance(&self) -> &Instance<C> {
+        self.instance
+    }
 
-        for (idx, diff) in self.candidate_list.iter() {
-            let (i, j) = calculate_swap_indices(self.instance.size as isize, *idx as isize);
-            if *diff < best_candidate_cost && self.tabu_list[i][j] == 0 {
-                best_candidate_cost = *diff;
-                best_candidate_idx = *idx
+    fn set_time_limit(&mut self, time_limit: u128) {
+        self.max_time = time_limit;
+    }
+
+    fn get_time_limit(&self) -> u128 {
+        self.max_time
+    }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn brute_force(instance: &Instance, perm: &mut Vec<usize>, depth: usize) -> usize {
+        if depth == perm.len() {
+            return instance.evaluate(perm).unwrap();
+        }
+        let mut best = usize::MAX;
+        for i in depth..perm.len() {
+            perm.swap(depth, i);
+            best = best.min(brute_force(instance, perm, depth + 1));
+            perm.swap(depth, i);
+        }
+        best
+    }
+
+    fn random_matrix(rows: usize, rng: &mut StdRng) -> Vec<Vec<usize>> {
+        (0..rows).map(|_| (0..rows).map(|_| rng.gen_range(0..20)).collect()).collect()
+    }
+
+    #[test]
+    fn tenure_stays_within_the_bounds() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for n in 1..=40 {
+            let instance = Instance::new(random_matrix(n, &mut rng), random_matrix(n, &mut rng), 0, vec![]);
+            let solver = TabuSearchSolver::new(&instance, 0, 0);
+            // The bounds are rounded outwards, so that small instances still get random tenures.
+            let (min_tenure, max_tenure) = ((MIN_TENURE_RATIO * n as f64).floor(), (MAX_TENURE_RATIO * n as f64).ceil());
+            for _ in 0..50 {
+                let tenure = solver.draw_tenure(&mut rng) as f64;
+                assert!(min_tenure <= tenure && tenure <= max_tenure, "{} not in [{}, {}]", tenure, min_tenure, max_tenure);
             }
         }
+    }
+
+    #[test]
+    fn aspiration_overrides_tabu_for_new_best() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let n = 8;
+        let instance = Instance::new(random_matrix(n, &mut rng), random_matrix(n, &mut rng), 0, vec![]);
+        let solver = TabuSearchSolver::new(&instance, usize::MAX, u128::MAX);
+        let perm = instance.random_permutation(&mut rng);
+        let deltas = DeltaMatrix::new(&instance, &perm);
+        let mut moves: Vec<(usize, usize)> = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j))).collect();
+        moves.sort_by_key(|&(i, j)| std::cmp::Reverse(deltas.get(i, j)));
+        let (best, other) = (moves[0], moves[1]);
+        assert!(deltas.get(best.0, best.1) > deltas.get(other.0, other.1) && deltas.get(best.0, best.1) > 0);
+
+        // Every swap is tabu except `other`.
+        let mut tabu_list = TabuList::new(n);
+        (0..n).for_each(|facility| (0..n).for_each(|location| tabu_list.forbid(facility, location, 1)));
+        tabu_list.until[other.0][perm[other.1]] = 0;
+        let current_cost = instance.evaluate_wide(&perm);
+        let select = |best_cost| {
+            let chosen = solver.select_move(&deltas, &perm, &tabu_list, (current_cost, best_cost), &mut 0).unwrap();
+            (chosen.0, chosen.1)
+        };
+
+        assert_eq!(best, select(current_cost));
+        assert_eq!(other, select(current_cost - deltas.get(best.0, best.1)));
+    }
 
-        if best_candidate_cost < self.candidate_threshold_cost as isize {
-            (best_candidate_idx, best_candidate_cost)
-        } else {
-            self.candidate_list.clear();
-            self.generate_candidate_list();
-            self.choose_from_candidate_list()
+    #[test]
+    fn finds_optimum_of_small_instances() {
+        let mut rng = StdRng::seed_from_u64(16);
+        for n in 2..=7 {
+            let instance = Instance::new(random_matrix(n, &mut rng), random_matrix(n, &mut rng), 0, vec![]);
+            let mut solver = TabuSearchSolver::new(&instance, 50_000, u128::MAX);
+
+            let solution = solver.solve(instance.random_permutation(&mut rng), &mut rng).unwrap();
+
+            let optimum = brute_force(&instance, &mut (0..n).collect(), 0);
+            assert_eq!(optimum, instance.evaluate(&solution.permutation).unwrap());
         }
     }
 
-    fn update_tabu_list(&mut self, neighbour_idx: usize) {
-        let (i, j) = calculate_swap_indices(self.instance.size as isize, neighbour_idx as isize);
-        self.tabu_list[i][j] = TABU_TENURE;
+    #[test]
+    fn finds_optimum_of_small_rectangular_instances() {
+        let mut rng = StdRng::seed_from_u64(17);
+        for (facilities, size) in [(2, 5), (3, 6), (4, 7)] {
+            let instance = Instance::new(random_matrix(facilities, &mut rng), random_matrix(size, &mut rng), 0, vec![]);
+            let mut solver = TabuSearchSolver::new(&instance, 50_000, u128::MAX);
+
+            let solution = solver.solve(instance.random_permutation(&mut rng), &mut rng).unwrap();
+
+            let optimum = brute_force(&instance, &mut (0..size).collect(), 0);
+            assert_eq!(optimum, instance.evaluate(&solution.permutation).unwrap());
+        }
     }
 }
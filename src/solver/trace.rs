@@ -0,0 +1,158 @@
+//! Convergence traces: the cost of a run over time and evaluations, for drawing anytime
+//! curves.
+
+use serde::{Deserialize, Serialize};
+
+use super::monitor::{CancellationToken, Monitor, Observer};
+
+/// Which points of a run a solver records.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tracing {
+    #[default]
+    Off,
+    /// Every new best solution.
+    Improvements,
+    /// The first step after every `interval` nanoseconds.
+    Interval(u128),
+}
+
+/// State of a run at some point.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct TracePoint {
+    /// Nanoseconds since the run started.
+    pub elapsed: u128,
+    pub evaluations: usize,
+    /// Cost of the solution the search is at, which may be worse than the best one.
+    pub current_cost: f64,
+    pub best_cost: f64,
+}
+
+/// Follows a run on behalf of its `Monitor`: collects the trace, notifies the observer of new
+/// best solutions and tells whether the run was cancelled. Solvers call `record` after every
+/// step, which costs a comparison when tracing is off and nobody observes the run, check
+/// `is_cancelled` along with their time limit and call `finish` once they are done.
+pub struct Tracer {
+    tracing: Tracing,
+    observer: Option<Observer>,
+    cancellation: Option<CancellationToken>,
+    start: std::time::Instant,
+    next_sample: u128,
+    best_cost: f64,
+    points: Vec<TracePoint>,
+}
+
+impl Tracer {
+    pub fn new(monitor: &Monitor) -> Tracer {
+        Tracer {
+            tracing: monitor.tracing,
+            observer: monitor.observer.clone(),
+            cancellation: monitor.cancellation.clone(),
+            start: std::time::Instant::now(),
+            next_sample: 0,
+            best_cost: f64::INFINITY,
+            points: Vec::new(),
+        }
+    }
+
+    /// Records the state of the run when it is due: always at the first step, then on every
+    /// new best cost or after every interval. The observer sees every new best cost.
+    pub fn record(&mut self, evaluations: usize, current_cost: f64, best_cost: f64) {
+        let improved = best_cost < self.best_cost;
+        if improved {
+            self.best_cost = best_cost;
+        }
+        let due = match self.tracing {
+            Tracing::Off => false,
+            Tracing::Improvements => improved,
+            Tracing::Interval(interval) => {
+                let elapsed = self.start.elapsed().as_nanos();
+                let due = elapsed >= self.next_sample;
+                if due {
+                    self.next_sample = elapsed + interval.max(1);
+                }
+                due
+            }
+        };
+        let observed = improved && self.observer.is_some();
+        if !due && !observed {
+            return;
+        }
+
+        let point = self.point(evaluations, current_cost, best_cost);
+        if let (true, Some(observer)) = (observed, &self.observer) {
+            observer(&point);
+        }
+        if due {
+            self.points.push(point);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.as_ref().is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Cancellation token of the run, for the searches it starts itself.
+    pub fn get_cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// The recorded points followed by the final state of the run, which the observer also
+    /// sees if it has not seen its best cost yet.
+    pub fn finish(mut self, evaluations: usize, current_cost: f64, best_cost: f64) -> Vec<TracePoint> {
+        let point = self.point(evaluations, current_cost, best_cost);
+        if let (true, Some(observer)) = (best_cost < self.best_cost, &self.observer) {
+            observer(&point);
+        }
+        if self.tracing != Tracing::Off {
+            self.points.push(point);
+        }
+        self.points
+    }
+
+    fn point(&self, evaluations: usize, current_cost: f64, best_cost: f64) -> TracePoint {
+        TracePoint {
+            elapsed: self.start.elapsed().as_nanos(),
+            evaluations,
+            current_cost,
+            best_cost,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        generator::{li_pardalos, LipaKind},
+        solver::{
+            monitor::Monitor, simulated_annealing::SimulatedAnnealingSolver, tabu_search::TabuSearchSolver, Solver,
+        },
+    };
+
+    #[test]
+    fn traces_follow_the_runs() {
+        let instance = li_pardalos(10, LipaKind::B, 4);
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut solver = TabuSearchSolver::new(&instance, 20_000, u128::MAX);
+        assert!(solver.solve(instance.random_permutation(&mut rng), &mut rng).unwrap().trace.is_empty());
+
+        solver.set_monitor(Monitor::new().with_tracing(Tracing::Improvements));
+        let solution = solver.solve(instance.random_permutation(&mut rng), &mut rng).unwrap();
+        let last = solution.trace.last().unwrap();
+        assert_eq!(instance.evaluate(&solution.permutation).unwrap() as f64, last.best_cost);
+        assert_eq!(solution.evaluations, last.evaluations);
+        for pair in solution.trace[..solution.trace.len() - 1].windows(2) {
+            assert!(pair[1].best_cost < pair[0].best_cost);
+            assert!(pair[1].evaluations >= pair[0].evaluations && pair[1].elapsed >= pair[0].elapsed);
+        }
+
+        let mut solver = SimulatedAnnealingSolver::new(&instance, 5_000_000);
+        solver.set_monitor(Monitor::new().with_tracing(Tracing::Interval(1_000_000)));
+        let solution = solver.solve(instance.random_permutation(&mut rng), &mut rng).unwrap();
+        assert!(solution.trace.len() >= 2);
+        assert!(solution.trace.iter().all(|point| point.best_cost <= point.current_cost));
+    }
+}
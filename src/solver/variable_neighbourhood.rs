@@ -0,0 +1,220 @@
+use rand::rngs::StdRng;
+
+use crate::{
+    cost::{Cost, CostOverflow},
+    instance::Instance,
+};
+
+use super::{
+    neighbourhood::{Neighbourhood, Swap, ThreeCycle},
+    monitor::Monitor,
+    trace::Tracer,
+    Solution, Solver, SolvingError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VnsNeighbourhood {
+    /// Exchange of the locations of two facilities.
+    Swap,
+    /// Rotation of the locations of three facilities.
+    ThreeCycle,
+    /// `k` random swaps applied to the best solution before the descent.
+    Shaking { k: usize },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Improvement<C: Cost = usize> {
+    pub neighbourhood: VnsNeighbourhood,
+    /// Cost of the solution right after the improvement.
+    pub cost: C,
+}
+
+/// Variable Neighbourhood Search. The best solution is shaken by `k` random swaps, with
+/// `k` growing from 1 to `max_shaking` while the search fails to improve, and the shaken
+/// solution descends by VND: first-improvement in the swap neighbourhood and, once it is
+/// exhausted, in the 3-cycle one, going back to swaps after every 3-cycle move.
+pub struct VariableNeighbourhoodSolver<'a, C: Cost = usize> {
+    instance: &'a Instance<C>,
+    max_time: u128,
+    monitor: Monitor,
+    max_shaking: usize,
+    improvements: Vec<Improvement<C>>,
+}
+
+impl<'a, C: Cost> VariableNeighbourhoodSolver<'a, C> {
+    pub fn new(instance: &Instance<C>, max_time: u128, max_shaking: usize) -> VariableNeighbourhoodSolver<'_, C> {
+        VariableNeighbourhoodSolver {
+            instance,
+            max_time,
+            monitor: Monitor::default(),
+            max_shaking,
+            improvements: Vec::new(),
+        }
+    }
+
+    /// Improvements of the last run in the order they happened. Swap and 3-cycle entries are
+    /// the moves of the descents; a shaking entry marks a new best solution found after
+    /// shaking with `k` swaps.
+    pub fn get_improvements(&self) -> &[Improvement<C>] {
+        &self.improvements
+    }
+
+    /// Number of improvements of the last run produced by `neighbourhood`.
+    pub fn get_improvement_count(&self, neighbourhood: VnsNeighbourhood) -> usize {
+        self.improvements
+            .iter()
+            .filter(|improvement| improvement.neighbourhood == neighbourhood)
+            .count()
+    }
+
+    /// Applies the first improving move of `neighbourhood`, returning its cost decrease.
+    fn improve<N: Neighbourhood>(
+        &self,
+        neighbourhood: &N,
+        perm: &mut [usize],
+        evaluations: &mut usize,
+        start: &std::time::Instant,
+    ) -> Option<C::Wide> {
+        let n = perm.len();
+        for idx in 0..neighbourhood.size(n) {
+            if idx % n == 0 && start.elapsed().as_nanos() >= self.max_time {
+                return None;
+            }
+            let mv = neighbourhood.nth_move(n, idx);
+            if !neighbourhood.is_feasible(self.instance, perm, mv) {
+                continue;
+            }
+            *evaluations += 1;
+            let delta = neighbourhood.delta(self.instance, perm, mv);
+            if delta > C::Wide::default() {
+                neighbourhood.apply(perm, mv);
+                return Some(delta);
+            }
+        }
+        None
+    }
+
+    /// Variable Neighbourhood Descent from `perm`, returning the cost of the local optimum.
+    fn descend(
+        &mut self,
+        perm: &mut [usize],
+        mut cost: C,
+        evaluations: &mut usize,
+        start: &std::time::Instant,
+    ) -> Result<C, CostOverflow> {
+        loop {
+            let (neighbourhood, delta) = if let Some(delta) = self.improve(&Swap, perm, evaluations, start) {
+                (VnsNeighbourhood::Swap, delta)
+            } else if let Some(delta) = self.improve(&ThreeCycle, perm, evaluations, start) {
+                (VnsNeighbourhood::ThreeCycle, delta)
+            } else {
+                return Ok(cost);
+            };
+            cost = C::narrow(cost.widen() - delta)?;
+            self.improvements.push(Improvement { neighbourhood, cost });
+        }
+    }
+}
+
+impl<'a, C: Cost> Solver<C> for VariableNeighbourhoodSolver<'a, C> {
+    fn solve(
+        &mut self,
+        initial_solution: Vec<usize>,
+        rng: &mut StdRng,
+    ) -> Result<Solution, SolvingError> {
+        let n = self.instance.size;
+        let start = std::time::Instant::now();
+        let mut tracer = Tracer::new(&self.monitor);
+        self.improvements.clear();
+
+        let mut evaluations = 1;
+        let mut best = initial_solution;
+        let initial_cost = self.instance.evaluate(&best)?;
+        let mut best_cost = self.descend(&mut best, initial_cost, &mut evaluations, &start)?;
+        let mut solution_changes = self.improvements.len();
+        tracer.record(evaluations, best_cost.to_f64(), best_cost.to_f64());
+
+        let mut k = 1;
+        while n > 1 && start.elapsed().as_nanos() < self.max_time && !tracer.is_cancelled() {
+            let mut candidate = best.clone();
+            for _ in 0..k {
+                if let Some(mv) = Swap.random_feasible_move(self.instance, &candidate, rng) {
+                    Swap.apply(&mut candidate, mv);
+                }
+            }
+            evaluations += 1;
+            let shaken_cost = self.instance.evaluate(&candidate)?;
+            let candidate_cost = self.descend(&mut candidate, shaken_cost, &mut evaluations, &start)?;
+
+            if candidate_cost < best_cost {
+                best = candidate;
+                best_cost = candidate_cost;
+                solution_changes += 1;
+                self.improvements.push(Improvement {
+                    neighbourhood: VnsNeighbourhood::Shaking { k },
+                    cost: best_cost,
+                });
+                k = 1;
+            } else {
+                k = if k >= self.max_shaking.max(1) { 1 } else { k + 1 };
+            }
+            tracer.record(evaluations, candidate_cost.to_f64(), best_cost.to_f64());
+        }
+
+        Ok(Solution {
+            permutation: best,
+            evaluations,
+            solution_changes,
+            trace: tracer.finish(evaluations, best_cost.to_f64(), best_cost.to_f64()),
+        })
+    }
+
+    fn get_name(&self) -> String {
+        "VariableNeighbourhoodSolver".to_string()
+    }
+
+    fn get_instance(&self) -> &Instance<C> {
+        self.instance
+    }
+
+    fn set_time_limit(&mut self, time_limit: u128) {
+        self.max_time = time_limit;
+    }
+
+    fn get_time_limit(&self) -> u128 {
+        self.max_time
+    }
+
+    fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = monitor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::{get_random_pair, get_random_permutation, solver::{eval_diff_reassign, rotate_three}};
+
+    #[test]
+    fn three_cycle_deltas_are_exact() {
+        let mut rng = StdRng::seed_from_u64(12);
+        let n = 9;
+        let mut random_matrix = || -> Vec<Vec<usize>> {
+            (0..n).map(|_| (0..n).map(|_| rng.gen_range(0..20)).collect()).collect()
+        };
+        let instance = Instance::new(random_matrix(), random_matrix(), 0, vec![]);
+
+        for _ in 0..200 {
+            let perm = get_random_permutation(n, &mut rng);
+            let (i, j) = get_random_pair(n, &mut rng);
+            let k = (0..n).find(|&k| k != i && k != j).unwrap();
+            let delta = eval_diff_reassign(&instance, &perm, &[i, j, k], &[perm[j], perm[k], perm[i]]);
+
+            let mut rotated = perm.clone();
+            rotate_three(&mut rotated, i, j, k);
+            assert_eq!(instance.evaluate_wide(&perm) - instance.evaluate_wide(&rotated), delta);
+        }
+    }
+}